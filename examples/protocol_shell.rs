@@ -0,0 +1,133 @@
+extern crate waveshare_barcodescanner;
+
+use std::io::{BufRead, Write};
+
+use anyhow::{Result, anyhow, bail};
+use tracing_subscriber::{EnvFilter, Layer, layer::SubscriberExt, util::SubscriberInitExt};
+use waveshare_barcodescanner::{
+    builder::OutputFormat,
+    interface::BarcodeScanner,
+    protocol::{
+        FUNCTION_SAVE, complete_command, encode_raw_command, encode_read_command,
+        encode_write_command,
+    },
+};
+
+const HELP: &str = "\
+commands:
+  read <address> [count]      read registers, e.g. read 0x002C 1
+  write <address> <value>...  write registers, e.g. write 0x0000 0x51
+  save                        save the settings to flash
+  raw <byte>...               send a packet given in hex, e.g. raw 7e 00 07 01 00 e1 01
+                              the checksum is added if it is missing
+  help                        show this text
+  quit                        leave the shell
+numbers are decimal unless prefixed with 0x, raw bytes are always hex";
+
+/// interactive shell sending single command packets to the barcode scanner
+///
+/// usage: protocol_shell [serial port] [baud rate]
+fn main() -> Result<()> {
+    // console output
+    let console_layer = tracing_subscriber::fmt::layer()
+        .with_file(true)
+        .with_line_number(true)
+        .with_filter(EnvFilter::from_default_env());
+
+    tracing_subscriber::registry().with(console_layer).init();
+
+    let mut args = std::env::args().skip(1);
+    let serial_port = args.next().unwrap_or_else(|| String::from("/dev/ttyAMA0"));
+    let baud_rate = match args.next() {
+        Some(baud_rate) => baud_rate.parse()?,
+        None => 9600,
+    };
+    // keep the settings of the device as they are
+    let mut scanner = BarcodeScanner::builder()
+        .baud_rate(baud_rate)
+        .output_format(OutputFormat::Unchanged)
+        .open(&serial_port)?;
+    println!(
+        "connected to {} at {} baud, type help for the commands",
+        serial_port, baud_rate
+    );
+
+    let stdin = std::io::stdin();
+    loop {
+        print!("> ");
+        std::io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break;
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let command = match words.split_first() {
+            None => continue,
+            Some((&"quit" | &"exit", _)) => break,
+            Some((&"help", _)) => {
+                println!("{}", HELP);
+                continue;
+            }
+            Some((command, args)) => build_command(command, args),
+        };
+        let result = command.and_then(|command| {
+            println!("-> {}", hex(&command));
+            Ok(scanner.send_raw_frame(&command)?)
+        });
+        match result {
+            Ok(reply) => println!("<- {}", reply),
+            Err(err) => println!("error: {}", err),
+        }
+    }
+    Ok(())
+}
+
+/// build the command packet for a line typed into the shell
+fn build_command(command: &str, args: &[&str]) -> Result<Vec<u8>> {
+    match (command, args) {
+        ("read", [address]) => Ok(encode_read_command(parse_address(address)?, 1)?),
+        ("read", [address, count]) => Ok(encode_read_command(
+            parse_address(address)?,
+            parse_number(count)?,
+        )?),
+        ("write", [address, values @ ..]) if !values.is_empty() => {
+            let values = values
+                .iter()
+                .map(|value| Ok(u8::try_from(parse_number(value)?)?))
+                .collect::<Result<Vec<u8>>>()?;
+            Ok(encode_write_command(parse_address(address)?, &values)?)
+        }
+        ("save", []) => Ok(encode_raw_command(FUNCTION_SAVE, 0x0000, &[0x00])?),
+        ("raw", bytes) if !bytes.is_empty() => {
+            let bytes = bytes
+                .iter()
+                .map(|byte| Ok(u8::from_str_radix(byte.trim_start_matches("0x"), 16)?))
+                .collect::<Result<Vec<u8>>>()?;
+            Ok(complete_command(&bytes)?)
+        }
+        _ => bail!("invalid command '{}', type help for the commands", command),
+    }
+}
+
+/// parse a register address
+fn parse_address(value: &str) -> Result<u16> {
+    u16::try_from(parse_number(value)?).map_err(|_| anyhow!("invalid address '{}'", value))
+}
+
+/// parse a decimal number, or a hex number prefixed with 0x
+fn parse_number(value: &str) -> Result<usize> {
+    let number = match value.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    number.map_err(|_| anyhow!("invalid number '{}'", value))
+}
+
+/// format bytes as hex separated by spaces
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,1151 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, ready},
+    time::{Duration, Instant},
+};
+
+use chrono::NaiveDate;
+use futures_core::Stream;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    time::timeout,
+};
+use tokio_serial::{DataBits, FlowControl, Parity, SerialPortBuilderExt, SerialStream, StopBits};
+use tracing::{debug, trace};
+
+use crate::{
+    Barcodes, Error, IlluminationMode, OperationMode, Result, ScanArea, ScannerMode, Symbology,
+    SymbologyStatus, TargetLightMode,
+    protocol::{
+        BARCODE_RESULT_OPTIONS, DecoderEvent, END_OF_DATA_GAP, FUNCTION_SAVE,
+        PROTOCOL_RESULT_OPTIONS, REPLY_OVERHEAD, ReplyDecoder, SCAN_FRAME_HEADER,
+        SCAN_FRAME_OVERHEAD, barcode_type_writes, decode_date, decode_reply, decode_scan_frame,
+        decode_scan_timeout, decode_symbology_status, decode_version, encode_raw_command,
+        encode_read_command, encode_scan_timeout, encode_write_command, reply_deadline,
+        scan_frame_data_length,
+    },
+    registers::{
+        HW_VERSION, RESULT_OPTIONS, Register, SCAN_TIMEOUT, SCAN_TRIGGER, SW_DATE, SW_VERSION,
+        ScanAreaAndBarcodes, ScanTrigger, SymbologyEnable,
+    },
+    scan::{ScanEvent, StopHandle},
+};
+
+/// asynchronous (tokio) version of [`crate::interface::BarcodeScanner`]
+///
+/// # Notes
+///
+/// All methods are cancel safe: when a future is dropped before it completes, the remaining
+/// reply data is discarded before the next command is sent to the barcode scanner.
+pub struct AsyncBarcodeScanner<T: AsyncRead + AsyncWrite + Unpin = SerialStream> {
+    /// serial or USB port (or any other async byte stream) to communicate over
+    port: T,
+    /// timeout for a single scan (in manual or command mode), default is 5s
+    scan_timeout: Duration,
+    /// maximum time to wait for the next byte of a reply, default is 100ms
+    read_timeout: Duration,
+    /// set while an exchange is in progress, stale data has to be discarded if it was interrupted
+    exchange_pending: bool,
+    /// barcodes are sent in scan frames with a checksum instead of plain text
+    protocol_output: bool,
+    /// a sequence of scans has been dropped, scanning is stopped before the next command
+    stop_scan_pending: bool,
+    /// the payload of barcodes starts with an AIM symbology identifier
+    aim_id_prefix: bool,
+    /// time of the last scan trigger which did not return a barcode yet
+    triggered_at: Option<Instant>,
+}
+
+impl AsyncBarcodeScanner<SerialStream> {
+    /// open the serial port and initialize the necessary device options for scanning
+    ///
+    /// # Arguments
+    ///
+    /// * `serial_port_name` the device name of the serial port to open
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use waveshare_barcodescanner::async_interface::AsyncBarcodeScanner;
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() -> Result<()> {
+    ///     let mut scanner = AsyncBarcodeScanner::new("/dev/serial0").await?;
+    ///     scanner.start_scan().await?;
+    ///     if let Some(barcode) = scanner.read_barcode().await?.map(|scan| scan.barcode) {
+    ///         println!("data scanned: {}", barcode);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Notes
+    ///
+    /// The device is expected to be in UART mode, with the serial options set to 9600,8,N,1 (factory default).
+    pub async fn new(serial_port_name: &str) -> Result<Self> {
+        let port = tokio_serial::new(serial_port_name, 9600)
+            .data_bits(DataBits::Eight)
+            .stop_bits(StopBits::One)
+            .parity(Parity::None)
+            .flow_control(FlowControl::None)
+            .open_native_async()
+            .map_err(|err| Error::Io(err.into()))?;
+        AsyncBarcodeScanner::with_transport(port).await
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> AsyncBarcodeScanner<T> {
+    /// use an already opened byte stream and initialize the necessary device options for scanning
+    pub async fn with_transport(port: T) -> Result<Self> {
+        let mut scanner = AsyncBarcodeScanner {
+            port,
+            scan_timeout: Duration::from_secs(5),
+            read_timeout: Duration::from_millis(100),
+            // discard anything the device sent before we were connected
+            exchange_pending: true,
+            protocol_output: false,
+            stop_scan_pending: false,
+            aim_id_prefix: false,
+            triggered_at: None,
+        };
+        // in order for barcode payload decoding to work corectly, always set the decoding options
+        scanner
+            .send_write_command(RESULT_OPTIONS, &[BARCODE_RESULT_OPTIONS])
+            .await?;
+        Ok(scanner)
+    }
+
+    /// return a mutable reference to the underlying byte stream
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.port
+    }
+
+    /// consume the scanner and return the underlying byte stream
+    pub fn into_transport(self) -> T {
+        self.port
+    }
+
+    /// return the hardware version of the attached barcode scanner
+    pub async fn get_hw_version(&mut self) -> Result<String> {
+        let value = self.read_register_value(HW_VERSION).await?;
+        Ok(decode_version(value))
+    }
+
+    /// return the software version of the attached barcode scanner
+    pub async fn get_sw_version(&mut self) -> Result<String> {
+        let value = self.read_register_value(SW_VERSION).await?;
+        Ok(decode_version(value))
+    }
+
+    /// return the software date of the attached barcode scanner
+    pub async fn get_sw_date(&mut self) -> Result<NaiveDate> {
+        let year = self.read_register_value(SW_DATE).await?;
+        let month = self.read_register_value(SW_DATE + 1).await?;
+        let day = self.read_register_value(SW_DATE + 2).await?;
+        decode_date(year, month, day)
+    }
+
+    /// start scanning for barcodes
+    pub async fn start_scan(&mut self) -> Result<()> {
+        self.write_register(&ScanTrigger { scanning: true }).await?;
+        self.triggered_at = Some(Instant::now());
+        Ok(())
+    }
+
+    /// stop scanning for barcodes
+    ///
+    /// # Notes
+    ///
+    /// When a barcode has been scanned, the scanner automatically stops scanning in manual and command mode.
+    pub async fn stop_scan(&mut self) -> Result<()> {
+        self.triggered_at = None;
+        self.write_register(&ScanTrigger { scanning: false }).await
+    }
+
+    /// waits for a barcode payload to be returned from the scanner
+    ///
+    /// will return immediately when the payload has been read or the `scan_timeout` has been reached
+    ///
+    /// # Notes
+    ///
+    /// The end of a plain text payload is detected the same way as in
+    /// [`crate::interface::BarcodeScanner::read_barcode`], binary payloads should be read in
+    /// protocol output mode ([`AsyncBarcodeScanner::set_protocol_output`]).
+    pub async fn read_barcode(&mut self) -> Result<Option<ScanEvent>> {
+        self.discard_stale_data().await?;
+        let deadline = Instant::now() + self.scan_timeout;
+        // read the code ID (the scan frame header in protocol output mode)
+        let mut codeid_buffer: [u8; 1] = [0x00; 1];
+        if !self.read_byte_until(deadline, &mut codeid_buffer).await? {
+            return Ok(None);
+        }
+        self.exchange_pending = true;
+        if self.protocol_output {
+            // data in front of the scan frame header is dropped
+            loop {
+                if codeid_buffer[0] == SCAN_FRAME_HEADER[0] {
+                    self.read_exact_with_timeout(&mut codeid_buffer).await?;
+                    if codeid_buffer[0] == SCAN_FRAME_HEADER[1] {
+                        break;
+                    }
+                    debug!("dropping unexpected data {:02X}", SCAN_FRAME_HEADER[0]);
+                    continue;
+                }
+                debug!("dropping unexpected data {:02X}", codeid_buffer[0]);
+                if !self.read_byte_until(deadline, &mut codeid_buffer).await? {
+                    self.exchange_pending = false;
+                    return Ok(None);
+                }
+            }
+            let mut frame: Vec<u8> = vec![SCAN_FRAME_HEADER[0], SCAN_FRAME_HEADER[1], 0x00];
+            self.read_exact_with_timeout(&mut frame[2..]).await?;
+            frame.resize(scan_frame_data_length(frame[2]) + SCAN_FRAME_OVERHEAD, 0x00);
+            self.read_exact_with_timeout(&mut frame[3..]).await?;
+            debug!("READ FROM SERIAL {} bytes: {:02X?}", frame.len(), frame);
+            self.exchange_pending = false;
+            let (code_id, payload) = decode_scan_frame(&frame)?;
+            return self.scan_event(code_id, payload);
+        }
+        // the payload ends with a CR which is not followed by more data within END_OF_DATA_GAP
+        let mut payload: Vec<u8> = Vec::with_capacity(256);
+        let mut buffer: [u8; 1] = [0; 1];
+        self.read_exact_with_timeout(&mut buffer).await?;
+        loop {
+            trace!("READ FROM SERIAL 1 bytes: {:02X?}", buffer);
+            payload.push(buffer[0]);
+            if buffer[0] == 0x0D {
+                match timeout(END_OF_DATA_GAP, self.port.read_exact(&mut buffer)).await {
+                    Ok(result) => {
+                        result?;
+                        continue;
+                    }
+                    Err(_) => {
+                        // remove the end character
+                        payload.pop();
+                        trace!("end of data detected, result len {}", payload.len());
+                        break;
+                    }
+                }
+            }
+            self.read_exact_with_timeout(&mut buffer).await?;
+        }
+        self.exchange_pending = false;
+        self.scan_event(codeid_buffer[0], payload)
+    }
+
+    /// the payload of barcodes starts with an AIM symbology identifier
+    ///
+    /// see [`crate::interface::BarcodeScanner::set_aim_id_prefix`]
+    pub fn set_aim_id_prefix(&mut self, enable: bool) {
+        self.aim_id_prefix = enable;
+    }
+
+    /// return true if the payload of barcodes is expected to start with an AIM symbology identifier
+    pub fn aim_id_prefix(&self) -> bool {
+        self.aim_id_prefix
+    }
+
+    /// decode the payload of a scan, the latency is measured from the last scan trigger
+    fn scan_event(&mut self, code_id: u8, payload: Vec<u8>) -> Result<Option<ScanEvent>> {
+        let scan = ScanEvent::decode(code_id, payload, self.aim_id_prefix, self.triggered_at)?;
+        if scan.is_some() {
+            self.triggered_at = None;
+        }
+        Ok(scan)
+    }
+
+    /// enable/disable the protocol output mode of the barcode scanner
+    ///
+    /// see [`crate::interface::BarcodeScanner::set_protocol_output`]
+    pub async fn set_protocol_output(&mut self, enable: bool) -> Result<()> {
+        let options = if enable {
+            PROTOCOL_RESULT_OPTIONS
+        } else {
+            BARCODE_RESULT_OPTIONS
+        };
+        self.send_write_command(RESULT_OPTIONS, &[options]).await?;
+        self.protocol_output = enable;
+        Ok(())
+    }
+
+    /// set the mode of operation and light/buzzer parameters
+    ///
+    /// see [`crate::interface::BarcodeScanner::set_mode`]
+    pub async fn set_mode(
+        &mut self,
+        enable_led_indication_on_successful_scan: bool,
+        enable_buzzer: bool,
+        target_light_mode: TargetLightMode,
+        illumination_mode: IlluminationMode,
+        operation_mode: OperationMode,
+    ) -> Result<()> {
+        self.write_register(&ScannerMode {
+            led_indication: enable_led_indication_on_successful_scan,
+            buzzer: enable_buzzer,
+            target_light_mode,
+            illumination_mode,
+            operation_mode,
+        })
+        .await
+    }
+
+    /// return the mode of operation and light/buzzer parameters
+    pub async fn get_mode(&mut self) -> Result<ScannerMode> {
+        self.read_register().await
+    }
+
+    /// set the scanning area and barcodes allowed
+    pub async fn set_scan_area_and_barcodes(
+        &mut self,
+        scan_area: ScanArea,
+        allowed_barcodes: Barcodes,
+    ) -> Result<()> {
+        self.write_register(&ScanAreaAndBarcodes {
+            scan_area,
+            barcodes: allowed_barcodes,
+        })
+        .await
+    }
+
+    /// return the scanning area and barcodes allowed
+    pub async fn get_scan_area_and_barcodes(&mut self) -> Result<(ScanArea, Barcodes)> {
+        let register: ScanAreaAndBarcodes = self.read_register().await?;
+        Ok((register.scan_area, register.barcodes))
+    }
+
+    /// return the scanning area
+    pub async fn get_scan_area(&mut self) -> Result<ScanArea> {
+        Ok(self.get_scan_area_and_barcodes().await?.0)
+    }
+
+    /// set the maximum time for a manual or command scan before the scanner goes inactive again
+    ///
+    /// see [`crate::interface::BarcodeScanner::set_scan_timeout`]
+    pub async fn set_scan_timeout(&mut self, scan_timeout: Duration) -> Result<()> {
+        let scan_timeout_byte = encode_scan_timeout(scan_timeout)?;
+        self.send_write_command(SCAN_TIMEOUT, &[scan_timeout_byte])
+            .await?;
+        self.scan_timeout = scan_timeout;
+        Ok(())
+    }
+
+    /// return the scan timeout the barcode scanner is configured for (zero for no timeout)
+    pub async fn get_scan_timeout(&mut self) -> Result<Duration> {
+        Ok(decode_scan_timeout(
+            self.read_register_value(SCAN_TIMEOUT).await?,
+        ))
+    }
+
+    /// enable/disable barcode type: EAN13
+    pub async fn allow_ean13(&mut self, enable: bool) -> Result<()> {
+        self.allow_barcode_type(Symbology::EAN13.enable_address(), enable.into(), None, None)
+            .await
+    }
+
+    /// enable/disable barcode type: EAN8
+    pub async fn allow_ean8(&mut self, enable: bool) -> Result<()> {
+        self.allow_barcode_type(Symbology::EAN8.enable_address(), enable.into(), None, None)
+            .await
+    }
+
+    /// enable/disable barcode type: GS1 Databar Stacked(RSS)
+    pub async fn allow_rss_stack(&mut self, enable: bool) -> Result<()> {
+        self.allow_barcode_type(
+            Symbology::GS1DatabarStacked.enable_address(),
+            enable.into(),
+            None,
+            None,
+        )
+        .await
+    }
+
+    /// enable/disable barcode type: GS1 Databar(RSS-14)
+    pub async fn allow_rss14(&mut self, enable: bool) -> Result<()> {
+        self.allow_barcode_type(
+            Symbology::GS1Databar.enable_address(),
+            enable.into(),
+            None,
+            None,
+        )
+        .await
+    }
+
+    /// enable/disable barcode type: GS1 Databar Limited(RSS)
+    pub async fn allow_limited_rss(&mut self, enable: bool) -> Result<()> {
+        self.allow_barcode_type(
+            Symbology::GS1DatabarLimited.enable_address(),
+            enable.into(),
+            None,
+            None,
+        )
+        .await
+    }
+
+    /// enable/disable barcode type: GS1 Databar Expanded(RSS)
+    ///
+    /// optionally specify the minimum and/or maximum number of characters expected in a valid barcode,
+    /// all others will be ignored
+    pub async fn allow_expanded_rss(
+        &mut self,
+        enable: bool,
+        min_length: Option<u8>,
+        max_length: Option<u8>,
+    ) -> Result<()> {
+        self.allow_barcode_type(
+            Symbology::GS1DatabarExpanded.enable_address(),
+            enable.into(),
+            min_length,
+            max_length,
+        )
+        .await
+    }
+
+    /// enable/disable barcode type: MSI-Plessey
+    ///
+    /// optionally specify the minimum and/or maximum number of characters expected in a valid barcode,
+    /// all others will be ignored
+    pub async fn allow_msi(
+        &mut self,
+        enable: bool,
+        min_length: Option<u8>,
+        max_length: Option<u8>,
+    ) -> Result<()> {
+        self.allow_barcode_type(
+            Symbology::MSI.enable_address(),
+            enable.into(),
+            min_length,
+            max_length,
+        )
+        .await
+    }
+
+    /// enable/disable barcode type: Code11
+    ///
+    /// optionally specify the minimum and/or maximum number of characters expected in a valid barcode,
+    /// all others will be ignored
+    pub async fn allow_code11(
+        &mut self,
+        enable: bool,
+        min_length: Option<u8>,
+        max_length: Option<u8>,
+    ) -> Result<()> {
+        self.allow_barcode_type(
+            Symbology::Code11.enable_address(),
+            enable.into(),
+            min_length,
+            max_length,
+        )
+        .await
+    }
+
+    /// enable/disable barcode type: Matrix 2of5
+    ///
+    /// optionally specify the minimum and/or maximum number of characters expected in a valid barcode,
+    /// all others will be ignored
+    pub async fn allow_matrix2of5(
+        &mut self,
+        enable: bool,
+        min_length: Option<u8>,
+        max_length: Option<u8>,
+    ) -> Result<()> {
+        self.allow_barcode_type(
+            Symbology::Matrix2of5.enable_address(),
+            enable.into(),
+            min_length,
+            max_length,
+        )
+        .await
+    }
+
+    /// enable/disable barcode type: Industrial
+    ///
+    /// optionally specify the minimum and/or maximum number of characters expected in a valid barcode,
+    /// all others will be ignored
+    pub async fn allow_industrial(
+        &mut self,
+        enable: bool,
+        min_length: Option<u8>,
+        max_length: Option<u8>,
+    ) -> Result<()> {
+        self.allow_barcode_type(
+            Symbology::Industrial2of5.enable_address(),
+            enable.into(),
+            min_length,
+            max_length,
+        )
+        .await
+    }
+
+    /// enable/disable barcode type: CodeBar
+    ///
+    /// optionally specify the minimum and/or maximum number of characters expected in a valid barcode,
+    /// all others will be ignored
+    pub async fn allow_codebar(
+        &mut self,
+        enable: bool,
+        with_start_stop_character: bool,
+        min_length: Option<u8>,
+        max_length: Option<u8>,
+    ) -> Result<()> {
+        let mode =
+            SymbologyEnable::for_symbology(Symbology::Codabar, enable, with_start_stop_character);
+        self.allow_barcode_type(
+            Symbology::Codabar.enable_address(),
+            mode.encode(),
+            min_length,
+            max_length,
+        )
+        .await
+    }
+
+    /// enable/disable barcode type: Code128
+    ///
+    /// optionally specify the minimum and/or maximum number of characters expected in a valid barcode,
+    /// all others will be ignored
+    pub async fn allow_code128(
+        &mut self,
+        enable: bool,
+        min_length: Option<u8>,
+        max_length: Option<u8>,
+    ) -> Result<()> {
+        self.allow_barcode_type(
+            Symbology::Code128.enable_address(),
+            enable.into(),
+            min_length,
+            max_length,
+        )
+        .await
+    }
+
+    /// enable/disable barcode type: Code39
+    ///
+    /// optionally specify the minimum and/or maximum number of characters expected in a valid barcode,
+    /// all others will be ignored
+    pub async fn allow_code39(
+        &mut self,
+        enable: bool,
+        min_length: Option<u8>,
+        max_length: Option<u8>,
+    ) -> Result<()> {
+        self.allow_barcode_type(
+            Symbology::Code39.enable_address(),
+            enable.into(),
+            min_length,
+            max_length,
+        )
+        .await
+    }
+
+    /// enable/disable barcode type: Code93
+    ///
+    /// optionally specify the minimum and/or maximum number of characters expected in a valid barcode,
+    /// all others will be ignored
+    pub async fn allow_code93(
+        &mut self,
+        enable: bool,
+        min_length: Option<u8>,
+        max_length: Option<u8>,
+    ) -> Result<()> {
+        self.allow_barcode_type(
+            Symbology::Code93.enable_address(),
+            enable.into(),
+            min_length,
+            max_length,
+        )
+        .await
+    }
+
+    /// enable/disable barcode type: UPCA
+    pub async fn allow_upca(&mut self, enable: bool) -> Result<()> {
+        self.allow_barcode_type(Symbology::UPCA.enable_address(), enable.into(), None, None)
+            .await
+    }
+
+    /// enable/disable barcode type: UPCE0
+    pub async fn allow_upce0(&mut self, enable: bool) -> Result<()> {
+        self.allow_barcode_type(Symbology::UPCE0.enable_address(), enable.into(), None, None)
+            .await
+    }
+
+    /// enable/disable barcode type: UPCE1
+    pub async fn allow_upce1(&mut self, enable: bool) -> Result<()> {
+        self.allow_barcode_type(Symbology::UPCE1.enable_address(), enable.into(), None, None)
+            .await
+    }
+
+    /// enable/disable barcode type: PDF417
+    pub async fn allow_pdf417(&mut self, enable: bool) -> Result<()> {
+        self.allow_barcode_type(
+            Symbology::PDF417.enable_address(),
+            enable.into(),
+            None,
+            None,
+        )
+        .await
+    }
+
+    /// enable/disable barcode type: Micro PDF417
+    pub async fn allow_micro_pdf417(&mut self, enable: bool) -> Result<()> {
+        self.allow_barcode_type(
+            Symbology::MicroPDF417.enable_address(),
+            enable.into(),
+            None,
+            None,
+        )
+        .await
+    }
+
+    /// enable/disable barcode type: ISBN
+    pub async fn allow_micro_isbn(&mut self, enable: bool) -> Result<()> {
+        self.allow_barcode_type(Symbology::ISBN.enable_address(), enable.into(), None, None)
+            .await
+    }
+
+    /// enable/disable barcode type: ISSN
+    pub async fn allow_micro_issn(&mut self, enable: bool) -> Result<()> {
+        self.allow_barcode_type(Symbology::ISSN.enable_address(), enable.into(), None, None)
+            .await
+    }
+
+    /// enable/disable barcode type: Dot Matrix code
+    pub async fn allow_dotmatrix(&mut self, enable: bool) -> Result<()> {
+        self.allow_barcode_type(
+            Symbology::DotMatrix.enable_address(),
+            enable.into(),
+            None,
+            None,
+        )
+        .await
+    }
+
+    /// enable/disable barcode type: QR code
+    pub async fn allow_qr(&mut self, enable: bool) -> Result<()> {
+        self.allow_barcode_type(Symbology::QR.enable_address(), enable.into(), None, None)
+            .await
+    }
+
+    /// enable/disable barcode type: Micro QR
+    pub async fn allow_microqr(&mut self, enable: bool) -> Result<()> {
+        self.allow_barcode_type(
+            Symbology::MicroQR.enable_address(),
+            enable.into(),
+            None,
+            None,
+        )
+        .await
+    }
+
+    /// enable/disable barcode type: Interleaved 2 of 5
+    ///
+    /// optionally specify the minimum and/or maximum number of characters expected in a valid barcode,
+    /// all others will be ignored
+    pub async fn allow_interleaved2of5(
+        &mut self,
+        enable: bool,
+        min_length: Option<u8>,
+        max_length: Option<u8>,
+    ) -> Result<()> {
+        self.allow_barcode_type(
+            Symbology::Interleaved2of5.enable_address(),
+            enable.into(),
+            min_length,
+            max_length,
+        )
+        .await
+    }
+
+    /// return if a barcode type is enabled, together with its length limits
+    pub async fn is_allowed(&mut self, symbology: Symbology) -> Result<SymbologyStatus> {
+        let mut buffer: Vec<u8> = vec![0x00; if symbology.has_length_limits() { 3 } else { 1 }];
+        self.read_registers_into(symbology.enable_address(), &mut buffer)
+            .await?;
+        decode_symbology_status(symbology, &buffer)
+    }
+
+    /// save all pending changes to flash
+    pub async fn save_to_flash(&mut self) -> Result<()> {
+        let command =
+            crate::protocol::encode_command(FUNCTION_SAVE, 1, 0x0000, None, Some(&[0x00]))?;
+        let mut buffer: [u8; 1] = [0x00; 1];
+        self.exchange(&command, &mut buffer).await?;
+        Ok(())
+    }
+
+    /// read `length` consecutive registers starting at `address`
+    ///
+    /// see [`crate::interface::BarcodeScanner::read_registers`]
+    pub async fn read_registers(&mut self, address: u16, length: usize) -> Result<Vec<u8>> {
+        if length == 0 {
+            return Err(Error::InvalidArgument(String::from(
+                "at least one register has to be read",
+            )));
+        }
+        let mut values: Vec<u8> = vec![0x00; length];
+        self.read_registers_into(address, &mut values).await?;
+        Ok(values)
+    }
+
+    /// write `values` to consecutive registers starting at `address`
+    ///
+    /// see [`crate::interface::BarcodeScanner::write_registers`]
+    pub async fn write_registers(&mut self, address: u16, values: &[u8]) -> Result<()> {
+        if values.is_empty() {
+            return Err(Error::InvalidArgument(String::from(
+                "at least one register has to be written",
+            )));
+        }
+        self.send_write_command(address, values).await
+    }
+
+    /// send a command packet with any function type and return the data of the reply
+    ///
+    /// see [`crate::interface::BarcodeScanner::send_raw_command`]
+    pub async fn send_raw_command(
+        &mut self,
+        function_type: u8,
+        address: u16,
+        data: &[u8],
+    ) -> Result<Vec<u8>> {
+        let command = encode_raw_command(function_type, address, data)?;
+        let mut buffer: Vec<u8> = vec![0x00; 256];
+        let bytes_read = self.exchange(&command, &mut buffer).await?;
+        buffer.truncate(bytes_read);
+        Ok(buffer)
+    }
+
+    /// read a register and convert it into its typed value
+    ///
+    /// see [`crate::interface::BarcodeScanner::read_register`]
+    pub async fn read_register<R: Register>(&mut self) -> Result<R> {
+        Ok(R::decode(self.read_register_value(R::ADDRESS).await?))
+    }
+
+    /// write the typed value of a register
+    ///
+    /// see [`crate::interface::BarcodeScanner::write_register`]
+    pub async fn write_register<R: Register>(&mut self, value: &R) -> Result<()> {
+        self.send_write_command(R::ADDRESS, &[value.encode()]).await
+    }
+
+    /// enable/disable a barcode type and optionally set its length limits
+    async fn allow_barcode_type(
+        &mut self,
+        enable_address: u16,
+        mode: u8,
+        min_length: Option<u8>,
+        max_length: Option<u8>,
+    ) -> Result<()> {
+        for (address, value) in barcode_type_writes(enable_address, mode, min_length, max_length) {
+            self.send_write_command(address, &[value]).await?;
+        }
+        Ok(())
+    }
+
+    /// read a single register from the barcode scanner
+    async fn read_register_value(&mut self, address: u16) -> Result<u8> {
+        let mut buffer: [u8; 1] = [0x00; 1];
+        self.read_registers_into(address, &mut buffer).await?;
+        Ok(buffer[0])
+    }
+
+    /// read `values.len()` consecutive registers starting at `address`
+    async fn read_registers_into(&mut self, address: u16, values: &mut [u8]) -> Result<()> {
+        let command = encode_read_command(address, values.len())?;
+        let bytes_read = self.exchange(&command, values).await?;
+        if bytes_read != values.len() {
+            return Err(Error::UnexpectedLength {
+                expected: values.len(),
+                received: bytes_read,
+            });
+        }
+        Ok(())
+    }
+
+    /// send a write command to the barcode scanner
+    async fn send_write_command(&mut self, address: u16, data: &[u8]) -> Result<()> {
+        let command = encode_write_command(address, data)?;
+        let mut buffer: [u8; 1] = [0x00; 1];
+        self.exchange(&command, &mut buffer).await?;
+        Ok(())
+    }
+
+    /// send a command packet and read the reply packet
+    ///
+    /// the `read_data` array needs to be allocated to the exact size of payload data expected
+    async fn exchange(&mut self, command: &[u8], read_data: &mut [u8]) -> Result<usize> {
+        if self.stop_scan_pending {
+            self.stop_scan_pending = false;
+            let stop_scan = ScanTrigger { scanning: false }.encode();
+            let stop_command = encode_write_command(SCAN_TRIGGER, &[stop_scan])?;
+            let mut buffer: [u8; 1] = [0x00; 1];
+            self.exchange_once(&stop_command, &mut buffer).await?;
+        }
+        self.exchange_once(command, read_data).await
+    }
+
+    /// send a single command packet and read its reply packet
+    async fn exchange_once(&mut self, command: &[u8], read_data: &mut [u8]) -> Result<usize> {
+        self.discard_stale_data().await?;
+        self.exchange_pending = true;
+        debug!("WRITE TO SERIAL {} bytes: {:02X?}", command.len(), command);
+        self.port.write_all(command).await?;
+        debug!("reading {} bytes", read_data.len() + REPLY_OVERHEAD);
+        let deadline = Instant::now() + reply_deadline(self.read_timeout);
+        let mut decoder = ReplyDecoder::new();
+        let mut buffer: [u8; 1] = [0x00; 1];
+        loop {
+            if Instant::now() >= deadline {
+                debug!("no reply received before the deadline");
+                return Err(Error::Timeout);
+            }
+            self.read_exact_with_timeout(&mut buffer).await?;
+            match decoder.push(buffer[0]) {
+                None => {}
+                Some(Ok(DecoderEvent::Garbage(garbage))) => {
+                    debug!("dropping unexpected data {:02X?}", garbage);
+                }
+                Some(Ok(DecoderEvent::Reply(packet))) => {
+                    debug!("READ FROM SERIAL {} bytes: {:02X?}", packet.len(), packet);
+                    self.exchange_pending = false;
+                    return decode_reply(&packet, read_data);
+                }
+                Some(Err(err)) => {
+                    self.exchange_pending = false;
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    /// read a single byte, return false if it did not arrive until `deadline`
+    async fn read_byte_until(&mut self, deadline: Instant, buffer: &mut [u8; 1]) -> Result<bool> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match timeout(remaining, self.port.read_exact(buffer)).await {
+            Ok(result) => {
+                result?;
+                Ok(true)
+            }
+            Err(_) => {
+                debug!("timeout waiting for barcode data");
+                Ok(false)
+            }
+        }
+    }
+
+    /// fill `buffer`, waiting at most `read_timeout` for each chunk of data
+    async fn read_exact_with_timeout(&mut self, buffer: &mut [u8]) -> Result<()> {
+        let mut filled = 0;
+        while filled < buffer.len() {
+            match timeout(self.read_timeout, self.port.read(&mut buffer[filled..])).await {
+                Ok(Ok(0)) => return Err(Error::Io(std::io::ErrorKind::UnexpectedEof.into())),
+                Ok(Ok(read_bytes)) => filled += read_bytes,
+                Ok(Err(err)) => return Err(err.into()),
+                Err(_) => return Err(Error::Timeout),
+            }
+        }
+        Ok(())
+    }
+
+    /// discard the remainder of an interrupted exchange
+    ///
+    /// reads until no more data arrives within `read_timeout`
+    async fn discard_stale_data(&mut self) -> Result<()> {
+        if !self.exchange_pending {
+            return Ok(());
+        }
+        let mut buffer: [u8; 64] = [0x00; 64];
+        loop {
+            match timeout(self.read_timeout, self.port.read(&mut buffer)).await {
+                Ok(Ok(0)) => return Err(Error::Io(std::io::ErrorKind::UnexpectedEof.into())),
+                Ok(Ok(read_bytes)) => {
+                    debug!(
+                        "discarding stale data {} bytes: {:02X?}",
+                        read_bytes,
+                        &buffer[..read_bytes]
+                    );
+                }
+                Ok(Err(err)) => return Err(err.into()),
+                Err(_) => break,
+            }
+        }
+        self.exchange_pending = false;
+        Ok(())
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> AsyncBarcodeScanner<T> {
+    /// return a stream of the barcodes read by the barcode scanner
+    ///
+    /// see [`AsyncScans`]
+    pub fn scans(&mut self) -> AsyncScans<'_, T> {
+        let stop = StopHandle::new();
+        AsyncScans {
+            state: Some(ScanState {
+                scanner: self,
+                stop: stop.clone(),
+                retrigger: None,
+                finished: false,
+            }),
+            pending: None,
+            stop,
+        }
+    }
+}
+
+/// future reading the next barcode, returning the state of the sequence of scans
+type ScanFuture<'a, T> =
+    Pin<Box<dyn Future<Output = (ScanState<'a, T>, Option<Result<ScanEvent>>)> + Send + 'a>>;
+
+/// stream of the barcodes read by an asynchronous barcode scanner
+///
+/// This is the asynchronous version of [`crate::scan::Scans`]: scans are triggered in command
+/// mode and read passively in all other operation modes. The stream ends after a transport
+/// error or when its [`StopHandle`] has been triggered, which is checked after each scan
+/// timeout. The stream can also be dropped at any time, scanning is then stopped before the
+/// next command is sent to the barcode scanner.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::future::poll_fn;
+/// use std::pin::Pin;
+///
+/// use anyhow::Result;
+/// use futures_core::Stream;
+/// use waveshare_barcodescanner::async_interface::AsyncBarcodeScanner;
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() -> Result<()> {
+///     let mut scanner = AsyncBarcodeScanner::new("/dev/serial0").await?;
+///     let mut scans = scanner.scans();
+///     while let Some(scan) = poll_fn(|cx| Pin::new(&mut scans).poll_next(cx)).await {
+///         println!("data scanned: {}", scan?.barcode);
+///     }
+///     Ok(())
+/// }
+/// ```
+pub struct AsyncScans<'a, T: AsyncRead + AsyncWrite + Unpin + Send> {
+    /// state of the sequence of scans while no barcode is being read
+    state: Option<ScanState<'a, T>>,
+    /// the read of the next barcode in progress
+    pending: Option<ScanFuture<'a, T>>,
+    /// ends the stream when triggered
+    stop: StopHandle,
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> AsyncScans<'_, T> {
+    /// return a handle to end the stream
+    pub fn stop_handle(&self) -> StopHandle {
+        self.stop.clone()
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for AsyncScans<'_, T> {
+    type Item = Result<ScanEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        if this.pending.is_none() {
+            let Some(mut state) = this.state.take() else {
+                return Poll::Ready(None);
+            };
+            this.pending = Some(Box::pin(async move {
+                let scan = state.next_scan().await;
+                (state, scan)
+            }));
+        }
+        let Some(pending) = this.pending.as_mut() else {
+            return Poll::Ready(None);
+        };
+        let (state, scan) = ready!(pending.as_mut().poll(cx));
+        this.pending = None;
+        if scan.is_some() {
+            this.state = Some(state);
+        }
+        Poll::Ready(scan)
+    }
+}
+
+/// state of a sequence of scans, moved into the future reading the next barcode
+struct ScanState<'a, T: AsyncRead + AsyncWrite + Unpin> {
+    /// the barcode scanner to read from
+    scanner: &'a mut AsyncBarcodeScanner<T>,
+    /// ends the stream when triggered
+    stop: StopHandle,
+    /// scans have to be triggered (command mode), None until the mode has been read
+    retrigger: Option<bool>,
+    /// set after a transport error
+    finished: bool,
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> ScanState<'_, T> {
+    /// read barcodes until one was received, an error occurred or the stream was stopped
+    async fn next_scan(&mut self) -> Option<Result<ScanEvent>> {
+        while !self.finished && !self.stop.is_stopped() {
+            match self.read_barcode().await {
+                Ok(Some(scan)) => return Some(Ok(scan)),
+                Ok(None) => {}
+                Err(err) => {
+                    // errors of the underlying transport are not recoverable
+                    self.finished = matches!(err, Error::Io(_));
+                    return Some(Err(err));
+                }
+            }
+        }
+        None
+    }
+
+    /// trigger a scan if needed and wait for a barcode for at most the scan timeout
+    async fn read_barcode(&mut self) -> Result<Option<ScanEvent>> {
+        let retrigger = match self.retrigger {
+            Some(retrigger) => retrigger,
+            None => {
+                let mode = self.scanner.get_mode().await?;
+                let retrigger = mode.operation_mode == OperationMode::Command;
+                self.retrigger = Some(retrigger);
+                retrigger
+            }
+        };
+        if retrigger {
+            self.scanner.start_scan().await?;
+        }
+        self.scanner.read_barcode().await
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> Drop for ScanState<'_, T> {
+    fn drop(&mut self) {
+        if self.retrigger.is_some() && !self.finished {
+            self.scanner.stop_scan_pending = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{future::poll_fn, task::Poll};
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream, duplex};
+
+    use super::*;
+    use crate::{Barcode, simulator::SimulatedScanner, transport::Transport};
+
+    /// connect a simulated barcode scanner to one end of an in-memory stream
+    fn simulated_port(mut simulator: SimulatedScanner) -> DuplexStream {
+        let (host, mut device) = duplex(1024);
+        simulator.set_read_timeout(Duration::ZERO).unwrap();
+        tokio::spawn(async move {
+            let mut buffer: [u8; 256] = [0x00; 256];
+            loop {
+                let read_bytes = match device.read(&mut buffer).await {
+                    Ok(0) | Err(_) => return,
+                    Ok(read_bytes) => read_bytes,
+                };
+                simulator.write_all(&buffer[..read_bytes]).unwrap();
+                while let Ok(reply_bytes) = simulator.read(&mut buffer) {
+                    if device.write_all(&buffer[..reply_bytes]).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        host
+    }
+
+    #[tokio::test]
+    async fn test_scan() -> Result<()> {
+        let mut simulator = SimulatedScanner::new();
+        simulator.queue_barcode(0x6A, b"0123456789")?;
+        let mut scanner = AsyncBarcodeScanner::with_transport(simulated_port(simulator)).await?;
+        assert_eq!(scanner.get_hw_version().await?, "V1.40");
+        scanner
+            .set_mode(
+                true,
+                false,
+                TargetLightMode::Standard,
+                IlluminationMode::Standard,
+                OperationMode::Command,
+            )
+            .await?;
+        assert_eq!(
+            scanner.get_mode().await?.operation_mode,
+            OperationMode::Command
+        );
+        scanner.allow_code128(true, Some(4), None).await?;
+        assert_eq!(
+            scanner.is_allowed(Symbology::Code128).await?.min_length,
+            Some(4)
+        );
+        scanner.start_scan().await?;
+        match scanner.read_barcode().await?.map(|scan| scan.barcode) {
+            Some(Barcode::Code128(data)) => assert_eq!(data, "0123456789"),
+            _ => panic!("expected a Code128 barcode"),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_protocol_output_scan() -> Result<()> {
+        let mut simulator = SimulatedScanner::new();
+        simulator.queue_barcode(0x51, b"line 1\r\nline 2")?;
+        let mut scanner = AsyncBarcodeScanner::with_transport(simulated_port(simulator)).await?;
+        scanner.set_protocol_output(true).await?;
+        scanner.start_scan().await?;
+        match scanner.read_barcode().await?.map(|scan| scan.barcode) {
+            Some(Barcode::QR(data)) => assert_eq!(data, "line 1\r\nline 2"),
+            _ => panic!("expected a QR code"),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_scans_stream() -> Result<()> {
+        let mut simulator = SimulatedScanner::new();
+        simulator.queue_barcode(0x51, b"FIRST")?;
+        simulator.queue_barcode(0x62, b"SECOND")?;
+        let mut scanner = AsyncBarcodeScanner::with_transport(simulated_port(simulator)).await?;
+        scanner
+            .set_mode(
+                false,
+                false,
+                TargetLightMode::Standard,
+                IlluminationMode::Standard,
+                OperationMode::Command,
+            )
+            .await?;
+        let mut scans = scanner.scans();
+        match poll_fn(|cx| Pin::new(&mut scans).poll_next(cx)).await {
+            Some(Ok(ScanEvent {
+                barcode: Barcode::QR(data),
+                ..
+            })) => assert_eq!(data, "FIRST"),
+            _ => panic!("expected a QR code"),
+        }
+        match poll_fn(|cx| Pin::new(&mut scans).poll_next(cx)).await {
+            Some(Ok(ScanEvent {
+                barcode: Barcode::Code39(data),
+                ..
+            })) => assert_eq!(data, "SECOND"),
+            _ => panic!("expected a Code39 barcode"),
+        }
+        // the stream is dropped while the third scan is running
+        assert!(poll_fn(|cx| Poll::Ready(Pin::new(&mut scans).poll_next(cx).is_pending())).await);
+        drop(scans);
+        assert_eq!(scanner.read_registers(SCAN_TRIGGER, 1).await?, [0x00]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_exchange() -> Result<()> {
+        let mut scanner =
+            AsyncBarcodeScanner::with_transport(simulated_port(SimulatedScanner::new())).await?;
+        // the command is sent, but the future is dropped before the reply has been read
+        let mut start_scan = Box::pin(scanner.start_scan());
+        assert!(poll_fn(|cx| Poll::Ready(start_scan.as_mut().poll(cx).is_pending())).await);
+        drop(start_scan);
+        assert_eq!(scanner.get_sw_version().await?, "V1.40");
+        Ok(())
+    }
+}
@@ -0,0 +1,218 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{Receiver, Sender, channel, sync_channel},
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use tracing::debug;
+
+use crate::{Error, Result, interface::BarcodeScanner, scan::ScanEvent, transport::Transport};
+
+/// a command executed on the reader thread
+type Request<T> = Box<dyn FnOnce(&mut BarcodeScanner<T>) + Send>;
+
+/// barcode scanner running on a background thread, continuously reading barcodes
+///
+/// This is meant for the continuous and sensing operation modes, where the scanner sends
+/// barcodes on its own. Scanned barcodes are delivered over a channel, while configuration
+/// commands can still be sent using [`BackgroundScanner::execute`]. Barcode data arriving in
+/// the middle of a command reply is kept and delivered as a regular scan.
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use waveshare_barcodescanner::{
+///     IlluminationMode, OperationMode, TargetLightMode, interface::BarcodeScanner,
+/// };
+///
+/// fn main() -> Result<()> {
+///     let mut scanner = BarcodeScanner::new("/dev/serial0")?;
+///     scanner.set_mode(
+///         false,
+///         true,
+///         TargetLightMode::Standard,
+///         IlluminationMode::Standard,
+///         OperationMode::Continuous,
+///     )?;
+///     let background = scanner.into_background();
+///     for scan in background.scans().iter().take(3) {
+///         println!("data scanned: {}", scan?);
+///     }
+///     background.execute(|scanner| scanner.allow_qr(false))?;
+///     let mut scanner = background.stop()?;
+///     scanner.stop_scan()?;
+///     Ok(())
+/// }
+/// ```
+pub struct BackgroundScanner<T: Transport + Send + 'static> {
+    /// commands to execute on the reader thread
+    requests: Sender<Request<T>>,
+    /// scanned barcodes (or read errors)
+    scans: Receiver<Result<ScanEvent>>,
+    /// signals the reader thread to finish
+    stop: Arc<AtomicBool>,
+    /// the reader thread, returning the scanner when finished
+    thread: Option<JoinHandle<BarcodeScanner<T>>>,
+}
+
+impl<T: Transport + Send + 'static> BackgroundScanner<T> {
+    /// move the barcode scanner to a new reader thread
+    pub fn spawn(scanner: BarcodeScanner<T>) -> Self {
+        let (requests, request_receiver) = channel::<Request<T>>();
+        let (scan_sender, scans) = channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let thread = std::thread::spawn(move || {
+            Self::run(scanner, request_receiver, scan_sender, thread_stop)
+        });
+        BackgroundScanner {
+            requests,
+            scans,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    /// return the channel the scanned barcodes are delivered on
+    pub fn scans(&self) -> &Receiver<Result<ScanEvent>> {
+        &self.scans
+    }
+
+    /// execute a command on the barcode scanner and wait for its result
+    ///
+    /// the command runs on the reader thread in between two barcode reads
+    pub fn execute<R, F>(&self, command: F) -> Result<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut BarcodeScanner<T>) -> Result<R> + Send + 'static,
+    {
+        let (result_sender, result_receiver) = sync_channel(1);
+        self.requests
+            .send(Box::new(move |scanner| {
+                let _ = result_sender.send(command(scanner));
+            }))
+            .map_err(|_| Error::ReaderStopped)?;
+        result_receiver.recv().map_err(|_| Error::ReaderStopped)?
+    }
+
+    /// stop the reader thread and return the barcode scanner
+    pub fn stop(mut self) -> Result<BarcodeScanner<T>> {
+        self.stop.store(true, Ordering::Relaxed);
+        match self.thread.take() {
+            Some(thread) => thread.join().map_err(|_| Error::ReaderStopped),
+            None => Err(Error::ReaderStopped),
+        }
+    }
+
+    /// main loop of the reader thread
+    fn run(
+        mut scanner: BarcodeScanner<T>,
+        requests: Receiver<Request<T>>,
+        scans: Sender<Result<ScanEvent>>,
+        stop: Arc<AtomicBool>,
+    ) -> BarcodeScanner<T> {
+        debug!("background reader started");
+        while !stop.load(Ordering::Relaxed) {
+            while let Ok(request) = requests.try_recv() {
+                request(&mut scanner);
+            }
+            // wait a single read timeout only, so commands are not delayed
+            let poll_timeout = scanner
+                .transport()
+                .read_timeout()
+                .unwrap_or(Duration::from_millis(100));
+            match scanner.read_barcode_within(poll_timeout) {
+                Ok(Some(scan)) => {
+                    let _ = scans.send(Ok(scan));
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    // errors of the underlying transport are not recoverable
+                    let fatal = matches!(err, Error::Io(_));
+                    let _ = scans.send(Err(err));
+                    if fatal {
+                        break;
+                    }
+                }
+            }
+        }
+        debug!("background reader stopped");
+        scanner
+    }
+}
+
+impl<T: Transport + Send + 'static> Drop for BackgroundScanner<T> {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl<T: Transport + Send + 'static> BarcodeScanner<T> {
+    /// move the barcode scanner to a background thread which continuously reads barcodes
+    ///
+    /// see [`BackgroundScanner`]
+    pub fn into_background(self) -> BackgroundScanner<T> {
+        BackgroundScanner::spawn(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Barcode, OperationMode,
+        simulator::{SimulatedScanner, test_scanner},
+    };
+
+    fn continuous_scanner() -> Result<BarcodeScanner<SimulatedScanner>> {
+        test_scanner(SimulatedScanner::new(), Some(OperationMode::Continuous))
+    }
+
+    #[test]
+    fn test_barcode_during_command_reply() -> Result<()> {
+        let mut scanner = continuous_scanner()?;
+        // the barcode is sent by the scanner right before the reply of the next command
+        scanner.transport_mut().send_barcode(0x62, b"CODE39")?;
+        assert_eq!(scanner.get_hw_version()?, "V1.40");
+        match scanner.read_barcode()?.map(|scan| scan.barcode) {
+            Some(Barcode::Code39(data)) => assert_eq!(data, "CODE39"),
+            _ => panic!("expected a Code39 barcode"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_background_scans_and_commands() -> Result<()> {
+        let mut scanner = continuous_scanner()?;
+        scanner
+            .transport_mut()
+            .queue_barcode(0x64, b"4006381333931")?;
+        scanner.transport_mut().queue_barcode(0x51, b"QR")?;
+        let background = scanner.into_background();
+        let timeout = Duration::from_secs(1);
+        match background.scans().recv_timeout(timeout).unwrap()?.barcode {
+            Barcode::EAN13(data) => assert_eq!(data, "4006381333931"),
+            _ => panic!("expected an EAN13 barcode"),
+        }
+        assert_eq!(
+            background.execute(|scanner| scanner.get_sw_version())?,
+            "V1.40"
+        );
+        background.execute(|scanner| scanner.allow_qr(false))?;
+        match background.scans().recv_timeout(timeout).unwrap()?.barcode {
+            Barcode::QR(data) => assert_eq!(data, "QR"),
+            _ => panic!("expected a QR code"),
+        }
+        let scanner = background.stop()?;
+        assert_eq!(scanner.transport().register(0x003F), 0x00);
+        Ok(())
+    }
+}
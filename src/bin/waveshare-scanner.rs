@@ -0,0 +1,506 @@
+use std::{collections::BTreeMap, error::Error, path::PathBuf, process::ExitCode, time::Duration};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use serial2::SerialPort;
+use waveshare_barcodescanner::{
+    Barcodes, IlluminationMode, OperationMode, ScanArea, Symbology, SymbologyStatus,
+    TargetLightMode,
+    builder::OutputFormat,
+    interface::BarcodeScanner,
+    profile::ScannerProfile,
+    protocol::output_options_supported,
+    registers::{Register, ResultOptions},
+    scan::ScanEvent,
+};
+
+/// result type of the subcommands, any error ends the program
+type CliResult<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// administration of Waveshare barcode scanner modules (UART mode)
+#[derive(Debug, Parser)]
+#[command(name = "waveshare-scanner", version)]
+struct Cli {
+    /// serial port the barcode scanner is connected to
+    #[arg(short, long)]
+    port: String,
+    /// speed of the serial port, it has to match the setting of the device
+    #[arg(short, long, default_value_t = 9600)]
+    baud: u32,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// show the hardware and software version and the software date
+    Info,
+    /// scan a barcode, or keep scanning with --loop
+    Scan {
+        /// keep scanning until the program is interrupted
+        #[arg(long = "loop")]
+        repeat: bool,
+        /// print each scan as a JSON object
+        #[arg(long)]
+        json: bool,
+    },
+    /// show a setting, or all settings if none is given
+    Get { setting: Option<SettingName> },
+    /// change a setting
+    Set { setting: SettingName, value: String },
+    /// enable a barcode type
+    Allow {
+        /// name of the barcode type, e.g. code128 or qr
+        #[arg(value_parser = parse_symbology)]
+        symbology: Symbology,
+        /// minimum number of characters (barcode types with length limits)
+        #[arg(long)]
+        min: Option<u8>,
+        /// maximum number of characters (barcode types with length limits)
+        #[arg(long)]
+        max: Option<u8>,
+        /// Codabar only: keep the start/stop characters in the payload
+        #[arg(long)]
+        start_stop: bool,
+    },
+    /// disable a barcode type
+    Deny {
+        /// name of the barcode type, e.g. code128 or qr
+        #[arg(value_parser = parse_symbology)]
+        symbology: Symbology,
+    },
+    /// write all settings as a profile to a .toml or .json file, or as TOML to stdout
+    Dump { file: Option<PathBuf> },
+    /// apply the settings of a profile (.toml or .json file)
+    Restore { file: PathBuf },
+    /// save the current settings to flash
+    Save,
+    /// restore the factory settings
+    FactoryReset,
+}
+
+/// settings which can be read and changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SettingName {
+    /// LED indication of a successful scan (on/off)
+    Led,
+    /// buzzer confirming a successful scan (on/off)
+    Buzzer,
+    /// target light mode (always-off, always-on, standard)
+    TargetLight,
+    /// illumination mode (always-off, always-on, standard)
+    Illumination,
+    /// operation mode (manual, command, continuous, sensing)
+    Operation,
+    /// scan area (all, center)
+    ScanArea,
+    /// barcode types recognized in general (enable-all, disable-all, default)
+    Barcodes,
+    /// scan timeout in milliseconds, 0 for no timeout
+    ScanTimeout,
+    /// setting changes via barcode scanning (on/off)
+    SettingScanning,
+    /// speed of the serial interface of the device
+    BaudRate,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// open the barcode scanner and execute the subcommand
+fn run(cli: Cli) -> CliResult<()> {
+    // the barcode result options are only changed when scanning requires it
+    let mut scanner = BarcodeScanner::builder()
+        .baud_rate(cli.baud)
+        .output_format(OutputFormat::Unchanged)
+        .open(&cli.port)?;
+    match cli.command {
+        Command::Info => {
+            println!("hardware version: {}", scanner.get_hw_version()?);
+            println!("software version: {}", scanner.get_sw_version()?);
+            println!(
+                "software date:    {}",
+                scanner.get_sw_date()?.format("%Y-%m-%d")
+            );
+        }
+        Command::Scan { repeat, json } => scan(&mut scanner, repeat, json)?,
+        Command::Get { setting: None } => {
+            for setting in SettingName::value_variants() {
+                print_setting(&mut scanner, *setting)?;
+            }
+        }
+        Command::Get {
+            setting: Some(setting),
+        } => print_setting(&mut scanner, setting)?,
+        Command::Set { setting, value } => set_setting(&mut scanner, setting, &value)?,
+        Command::Allow {
+            symbology,
+            min,
+            max,
+            start_stop,
+        } => {
+            let status = SymbologyStatus {
+                enabled: true,
+                start_stop_character: start_stop,
+                min_length: min,
+                max_length: max,
+            };
+            apply_symbology(&mut scanner, symbology, status)?;
+        }
+        Command::Deny { symbology } => {
+            apply_symbology(&mut scanner, symbology, SymbologyStatus::default())?;
+        }
+        Command::Dump { file } => {
+            let profile = ScannerProfile::from_scanner(&mut scanner)?;
+            match file {
+                Some(file) => profile.save(file)?,
+                None => print!("{}", profile.to_toml()?),
+            }
+        }
+        Command::Restore { file } => ScannerProfile::load(file)?.apply_profile(&mut scanner)?,
+        Command::Save => scanner.save_to_flash()?,
+        Command::FactoryReset => scanner.factory_reset()?,
+    }
+    Ok(())
+}
+
+/// read a single barcode, or keep reading barcodes if `repeat` is set
+fn scan(scanner: &mut BarcodeScanner<SerialPort>, repeat: bool, json: bool) -> CliResult<()> {
+    prepare_output_format(scanner)?;
+    if repeat {
+        for scan in scanner.scans() {
+            print_scan(&scan?, json)?;
+        }
+        return Ok(());
+    }
+    scanner.start_scan()?;
+    match scanner.read_barcode()? {
+        Some(scan) => print_scan(&scan, json),
+        None => Err("no barcode scanned".into()),
+    }
+}
+
+/// follow the output format of the device, plain text is only set up if it is not supported
+fn prepare_output_format(scanner: &mut BarcodeScanner<SerialPort>) -> CliResult<()> {
+    let options = scanner.read_register::<ResultOptions>()?;
+    if !output_options_supported(options.encode()) {
+        eprintln!("switching the barcode result options to plain text with code ID");
+        scanner.set_protocol_output(false)?;
+    } else if options.protocol {
+        scanner.set_protocol_output(true)?;
+    }
+    Ok(())
+}
+
+/// print a scan as text or as JSON object
+fn print_scan(scan: &ScanEvent, json: bool) -> CliResult<()> {
+    if json {
+        println!("{}", serde_json::to_string(scan)?);
+    } else {
+        println!("{}", scan.barcode);
+    }
+    Ok(())
+}
+
+/// enable/disable a single barcode type
+fn apply_symbology(
+    scanner: &mut BarcodeScanner<SerialPort>,
+    symbology: Symbology,
+    status: SymbologyStatus,
+) -> CliResult<()> {
+    let profile = ScannerProfile {
+        symbologies: BTreeMap::from([(symbology, status)]),
+        ..ScannerProfile::default()
+    };
+    profile.apply_profile(scanner)?;
+    Ok(())
+}
+
+/// print the current value of a setting
+fn print_setting(scanner: &mut BarcodeScanner<SerialPort>, setting: SettingName) -> CliResult<()> {
+    let name = setting
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default();
+    let value = match setting {
+        SettingName::Led => on_off(scanner.get_mode()?.led_indication).to_string(),
+        SettingName::Buzzer => on_off(scanner.get_mode()?.buzzer).to_string(),
+        SettingName::TargetLight => {
+            name_of(&TARGET_LIGHT_MODES, scanner.get_mode()?.target_light_mode).to_string()
+        }
+        SettingName::Illumination => {
+            name_of(&ILLUMINATION_MODES, scanner.get_mode()?.illumination_mode).to_string()
+        }
+        SettingName::Operation => {
+            name_of(&OPERATION_MODES, scanner.get_mode()?.operation_mode).to_string()
+        }
+        SettingName::ScanArea => {
+            name_of(&SCAN_AREAS, scanner.get_scan_area_and_barcodes()?.0).to_string()
+        }
+        SettingName::Barcodes => {
+            name_of(&BARCODES, scanner.get_scan_area_and_barcodes()?.1).to_string()
+        }
+        SettingName::ScanTimeout => scanner.get_scan_timeout()?.as_millis().to_string(),
+        SettingName::SettingScanning => on_off(scanner.is_setting_scanning_enabled()?).to_string(),
+        SettingName::BaudRate => scanner.get_baud_rate()?.to_string(),
+    };
+    println!("{}: {}", name, value);
+    Ok(())
+}
+
+/// change a setting, the other settings stored in the same register are kept
+fn set_setting(
+    scanner: &mut BarcodeScanner<SerialPort>,
+    setting: SettingName,
+    value: &str,
+) -> CliResult<()> {
+    match setting {
+        SettingName::Led
+        | SettingName::Buzzer
+        | SettingName::TargetLight
+        | SettingName::Illumination
+        | SettingName::Operation => {
+            let mut mode = scanner.get_mode()?;
+            match setting {
+                SettingName::Led => mode.led_indication = parse_on_off(value)?,
+                SettingName::Buzzer => mode.buzzer = parse_on_off(value)?,
+                SettingName::TargetLight => mode.target_light_mode = parse_target_light(value)?,
+                SettingName::Illumination => mode.illumination_mode = parse_illumination(value)?,
+                _ => mode.operation_mode = parse_operation(value)?,
+            }
+            scanner.set_mode(
+                mode.led_indication,
+                mode.buzzer,
+                mode.target_light_mode,
+                mode.illumination_mode,
+                mode.operation_mode,
+            )?;
+        }
+        SettingName::ScanArea => {
+            let (_, barcodes) = scanner.get_scan_area_and_barcodes()?;
+            scanner.set_scan_area_and_barcodes(parse_scan_area(value)?, barcodes)?;
+        }
+        SettingName::Barcodes => {
+            let (scan_area, _) = scanner.get_scan_area_and_barcodes()?;
+            scanner.set_scan_area_and_barcodes(scan_area, parse_barcodes(value)?)?;
+        }
+        SettingName::ScanTimeout => match value.parse()? {
+            0 => scanner.disable_scan_timeout()?,
+            scan_timeout_ms => scanner.set_scan_timeout(Duration::from_millis(scan_timeout_ms))?,
+        },
+        SettingName::SettingScanning => {
+            if parse_on_off(value)? {
+                scanner.enable_setting_scanning()?;
+            } else {
+                scanner.disable_setting_scanning()?;
+            }
+        }
+        SettingName::BaudRate => scanner.set_baud_rate(value.parse()?)?,
+    }
+    Ok(())
+}
+
+/// format a flag as on/off
+fn on_off(value: bool) -> &'static str {
+    if value { "on" } else { "off" }
+}
+
+/// parse a flag given as on/off, true/false, yes/no or 1/0
+fn parse_on_off(value: &str) -> Result<bool, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "on" | "true" | "yes" | "1" => Ok(true),
+        "off" | "false" | "no" | "0" => Ok(false),
+        _ => Err(format!("invalid value '{}', expected on or off", value)),
+    }
+}
+
+/// parse a barcode type by its name, ignoring case, e.g. code128 or QR
+fn parse_symbology(value: &str) -> Result<Symbology, String> {
+    Symbology::ALL
+        .into_iter()
+        .find(|symbology| format!("{:?}", symbology).eq_ignore_ascii_case(value))
+        .ok_or_else(|| format!("unknown barcode type '{}'", value))
+}
+
+/// names of the target light modes as printed and accepted on the command line
+const TARGET_LIGHT_MODES: [(&str, TargetLightMode); 3] = [
+    ("always-off", TargetLightMode::AlwaysOff),
+    ("always-on", TargetLightMode::AlwaysOn),
+    ("standard", TargetLightMode::Standard),
+];
+
+/// names of the illumination modes as printed and accepted on the command line
+const ILLUMINATION_MODES: [(&str, IlluminationMode); 3] = [
+    ("always-off", IlluminationMode::AlwaysOff),
+    ("always-on", IlluminationMode::AlwaysOn),
+    ("standard", IlluminationMode::Standard),
+];
+
+/// names of the operation modes as printed and accepted on the command line
+const OPERATION_MODES: [(&str, OperationMode); 4] = [
+    ("manual", OperationMode::Manual),
+    ("command", OperationMode::Command),
+    ("continuous", OperationMode::Continuous),
+    ("sensing", OperationMode::Sensing),
+];
+
+/// names of the scan areas as printed and accepted on the command line
+const SCAN_AREAS: [(&str, ScanArea); 2] = [("all", ScanArea::All), ("center", ScanArea::Center)];
+
+/// names of the barcode types recognized in general as printed and accepted on the command line
+const BARCODES: [(&str, Barcodes); 3] = [
+    ("enable-all", Barcodes::EnableAll),
+    ("disable-all", Barcodes::DisableAll),
+    ("default", Barcodes::Default),
+];
+
+/// return the command line name of `value`
+fn name_of<V: Copy + PartialEq>(names: &[(&'static str, V)], value: V) -> &'static str {
+    names
+        .iter()
+        .find(|(_, candidate)| *candidate == value)
+        .map(|(name, _)| *name)
+        .unwrap_or_default()
+}
+
+/// parse a value by its command line name, ignoring case
+fn parse_named<V: Copy>(names: &[(&str, V)], value: &str, kind: &str) -> Result<V, String> {
+    names
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(value))
+        .map(|(_, value)| *value)
+        .ok_or_else(|| format!("invalid {} '{}'", kind, value))
+}
+
+/// parse a target light mode (always-off, always-on, standard)
+fn parse_target_light(value: &str) -> Result<TargetLightMode, String> {
+    parse_named(&TARGET_LIGHT_MODES, value, "target light mode")
+}
+
+/// parse an illumination mode (always-off, always-on, standard)
+fn parse_illumination(value: &str) -> Result<IlluminationMode, String> {
+    parse_named(&ILLUMINATION_MODES, value, "illumination mode")
+}
+
+/// parse an operation mode (manual, command, continuous, sensing)
+fn parse_operation(value: &str) -> Result<OperationMode, String> {
+    parse_named(&OPERATION_MODES, value, "operation mode")
+}
+
+/// parse a scan area (all, center)
+fn parse_scan_area(value: &str) -> Result<ScanArea, String> {
+    parse_named(&SCAN_AREAS, value, "scan area")
+}
+
+/// parse the barcode types recognized in general (enable-all, disable-all, default)
+fn parse_barcodes(value: &str) -> Result<Barcodes, String> {
+    parse_named(&BARCODES, value, "barcodes setting")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_arguments() {
+        let cli = Cli::try_parse_from([
+            "waveshare-scanner",
+            "--port",
+            "/dev/ttyUSB0",
+            "allow",
+            "code128",
+            "--min",
+            "4",
+        ])
+        .unwrap();
+        assert_eq!(cli.baud, 9600);
+        assert!(matches!(
+            cli.command,
+            Command::Allow {
+                symbology: Symbology::Code128,
+                min: Some(4),
+                max: None,
+                start_stop: false,
+            }
+        ));
+        let cli = Cli::try_parse_from([
+            "waveshare-scanner",
+            "-p",
+            "/dev/ttyUSB0",
+            "-b",
+            "115200",
+            "set",
+            "scan-timeout",
+            "3000",
+        ])
+        .unwrap();
+        assert_eq!(cli.baud, 115200);
+        assert!(matches!(
+            cli.command,
+            Command::Set {
+                setting: SettingName::ScanTimeout,
+                ..
+            }
+        ));
+        assert!(Cli::try_parse_from(["waveshare-scanner", "-p", "x", "deny", "code42"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_values() {
+        assert_eq!(parse_on_off("ON"), Ok(true));
+        assert_eq!(parse_on_off("0"), Ok(false));
+        assert!(parse_on_off("maybe").is_err());
+        assert_eq!(parse_symbology("qr"), Ok(Symbology::QR));
+        assert_eq!(
+            parse_symbology("GS1DatabarLimited"),
+            Ok(Symbology::GS1DatabarLimited)
+        );
+        assert_eq!(parse_operation("Continuous"), Ok(OperationMode::Continuous));
+        assert_eq!(parse_barcodes("enable-all"), Ok(Barcodes::EnableAll));
+        assert!(parse_scan_area("left").is_err());
+    }
+
+    /// every value printed by `get` is accepted by `set` for the same setting
+    fn assert_round_trip<V: Copy + PartialEq + std::fmt::Debug>(
+        names: &[(&'static str, V)],
+        parse: fn(&str) -> Result<V, String>,
+    ) {
+        for (_, value) in names {
+            assert_eq!(parse(name_of(names, *value)), Ok(*value));
+        }
+    }
+
+    #[test]
+    fn test_setting_round_trip() {
+        for setting in SettingName::value_variants() {
+            match setting {
+                SettingName::Led | SettingName::Buzzer | SettingName::SettingScanning => {
+                    for value in [true, false] {
+                        assert_eq!(parse_on_off(on_off(value)), Ok(value));
+                    }
+                }
+                SettingName::TargetLight => {
+                    assert_round_trip(&TARGET_LIGHT_MODES, parse_target_light)
+                }
+                SettingName::Illumination => {
+                    assert_round_trip(&ILLUMINATION_MODES, parse_illumination)
+                }
+                SettingName::Operation => assert_round_trip(&OPERATION_MODES, parse_operation),
+                SettingName::ScanArea => assert_round_trip(&SCAN_AREAS, parse_scan_area),
+                SettingName::Barcodes => assert_round_trip(&BARCODES, parse_barcodes),
+                SettingName::ScanTimeout => {
+                    let printed = Duration::from_millis(3000).as_millis().to_string();
+                    assert_eq!(printed.parse::<u64>(), Ok(3000));
+                }
+                SettingName::BaudRate => assert_eq!(115200.to_string().parse::<u32>(), Ok(115200)),
+            }
+        }
+    }
+}
@@ -0,0 +1,410 @@
+use std::{
+    error::Error,
+    io::{BufRead, Write},
+    path::PathBuf,
+    process::ExitCode,
+    sync::mpsc::{self, Receiver},
+    time::Duration,
+};
+
+use clap::Parser;
+use waveshare_barcodescanner::{
+    OperationMode, ScannerMode,
+    registers::{MODE, Register},
+    simulator::SimulatedScanner,
+    transport::Transport,
+};
+
+/// result type of the program, any error ends it
+type CliResult<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// time to wait for commands from the host before checking for typed barcodes
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// emulated Waveshare barcode scanner module on a pseudo-terminal
+///
+/// The path of the pseudo-terminal is printed on startup. Barcodes typed on stdin, one per
+/// line as [CODE_ID:]DATA with the code ID in hex (e.g. 51:hello for a QR code), are sent
+/// right away in manual mode and returned on the next scan trigger otherwise.
+#[derive(Debug, Parser)]
+#[command(name = "waveshare-virtual-scanner", version)]
+struct Cli {
+    /// barcode to return on a scan trigger as [CODE_ID:]DATA, can be repeated
+    #[arg(short, long = "barcode", value_parser = parse_barcode)]
+    barcodes: Vec<ScriptedBarcode>,
+    /// file with barcodes to return on scan triggers, one per line as [CODE_ID:]DATA
+    #[arg(short, long)]
+    script: Option<PathBuf>,
+    /// code ID of barcodes given without one (hex)
+    #[arg(short, long, default_value = "6A", value_parser = parse_code_id)]
+    code_id: u8,
+    /// create a symbolic link to the pseudo-terminal, e.g. /tmp/ttyScanner
+    #[arg(short, long)]
+    link: Option<PathBuf>,
+}
+
+/// barcode sent by the emulated scanner
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ScriptedBarcode {
+    /// code ID identifying the barcode type, None for the default one
+    code_id: Option<u8>,
+    /// payload of the barcode
+    data: Vec<u8>,
+}
+
+impl ScriptedBarcode {
+    /// return the code ID, or `default` if none was given
+    fn code_id_or(&self, default: u8) -> u8 {
+        self.code_id.unwrap_or(default)
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// open the pseudo-terminal and emulate the scanner until the program is interrupted
+#[cfg(unix)]
+fn run(cli: Cli) -> CliResult<()> {
+    let mut simulator = SimulatedScanner::new();
+    let mut barcodes = cli.barcodes;
+    if let Some(script) = &cli.script {
+        for line in std::fs::read_to_string(script)?.lines() {
+            if !line.is_empty() {
+                barcodes.push(parse_barcode(line)?);
+            }
+        }
+    }
+    for barcode in &barcodes {
+        simulator.queue_barcode(barcode.code_id_or(cli.code_id), &barcode.data)?;
+    }
+
+    let terminal = pty::PseudoTerminal::open()?;
+    if let Some(link) = &cli.link {
+        replace_link(link, terminal.path())?;
+    }
+    println!("{}", terminal.path());
+    std::io::stdout().flush()?;
+
+    let (sender, typed) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            match parse_barcode(&line) {
+                Ok(barcode) if !barcode.data.is_empty() => {
+                    if sender.send(barcode).is_err() {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => eprintln!("error: {}", err),
+            }
+        }
+    });
+    serve(&terminal, simulator, &typed, cli.code_id)
+}
+
+#[cfg(not(unix))]
+fn run(_cli: Cli) -> CliResult<()> {
+    Err("pseudo-terminals are only supported on Unix".into())
+}
+
+/// create a symbolic link to `target`, replacing a link left over from an earlier run
+///
+/// any other file at `link` is kept and reported as error
+#[cfg(unix)]
+fn replace_link(link: &std::path::Path, target: &str) -> CliResult<()> {
+    match std::fs::symlink_metadata(link) {
+        Ok(metadata) if metadata.file_type().is_symlink() => std::fs::remove_file(link)?,
+        Ok(_) => {
+            return Err(format!("{} exists and is not a symbolic link", link.display()).into());
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err.into()),
+    }
+    std::os::unix::fs::symlink(target, link)?;
+    Ok(())
+}
+
+/// pass the commands of the host to the simulator and its replies and barcodes back
+#[cfg(unix)]
+fn serve(
+    terminal: &pty::PseudoTerminal,
+    mut simulator: SimulatedScanner,
+    typed: &Receiver<ScriptedBarcode>,
+    default_code_id: u8,
+) -> CliResult<()> {
+    // the loop waits for the host, not the simulator
+    simulator.set_read_timeout(Duration::ZERO)?;
+    let mut buffer = [0x00; 512];
+    loop {
+        for barcode in typed.try_iter() {
+            let code_id = barcode.code_id_or(default_code_id);
+            // in manual mode typing a barcode is like pressing the button
+            let result = if ScannerMode::decode(simulator.register(MODE)).operation_mode
+                == OperationMode::Manual
+            {
+                simulator.send_barcode(code_id, &barcode.data)
+            } else {
+                simulator.queue_barcode(code_id, &barcode.data)
+            };
+            if let Err(err) = result {
+                eprintln!("error: {}", err);
+            }
+        }
+        let count = terminal.read(&mut buffer, POLL_INTERVAL)?;
+        if count > 0 {
+            // the simulator ignores commands sent with another speed than it is configured for
+            simulator.set_baud_rate(terminal.baud_rate()?)?;
+            simulator.write_all(&buffer[..count])?;
+        }
+        loop {
+            match simulator.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(count) => terminal.write_all(&buffer[..count])?,
+                Err(err) if err.kind() == std::io::ErrorKind::TimedOut => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+/// parse a code ID given in hex, e.g. 6A or 0x6A
+fn parse_code_id(value: &str) -> Result<u8, String> {
+    u8::from_str_radix(value.trim_start_matches("0x"), 16)
+        .map_err(|_| format!("invalid code ID '{}', expected a hex byte", value))
+}
+
+/// parse a barcode given as [CODE_ID:]DATA, the code ID has two hex digits
+fn parse_barcode(value: &str) -> Result<ScriptedBarcode, String> {
+    match value.split_once(':') {
+        Some((code_id, data)) if code_id.len() == 2 => Ok(ScriptedBarcode {
+            code_id: Some(parse_code_id(code_id)?),
+            data: data.as_bytes().to_vec(),
+        }),
+        _ => Ok(ScriptedBarcode {
+            code_id: None,
+            data: value.as_bytes().to_vec(),
+        }),
+    }
+}
+
+/// pseudo-terminal access based on the POSIX functions
+#[cfg(unix)]
+mod pty {
+    use std::{
+        ffi::CStr,
+        io,
+        mem::MaybeUninit,
+        os::fd::{AsRawFd, FromRawFd, OwnedFd},
+        time::Duration,
+    };
+
+    /// pseudo-terminal, the emulator uses the master side and the host the slave side
+    pub struct PseudoTerminal {
+        /// master side, read and written by the emulator
+        master: OwnedFd,
+        /// slave side, kept open so the master stays usable while no host is connected
+        slave: OwnedFd,
+        /// path of the slave side, e.g. /dev/pts/3
+        path: String,
+    }
+
+    /// convert the return value of a libc function into an error
+    fn check(result: libc::c_int) -> io::Result<libc::c_int> {
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(result)
+    }
+
+    impl PseudoTerminal {
+        /// create a pseudo-terminal with the slave side in raw mode
+        pub fn open() -> io::Result<Self> {
+            // SAFETY: the file descriptors are checked before they are wrapped, the name returned
+            // by ptsname is copied before any other call, the termios struct is initialized by
+            // tcgetattr
+            unsafe {
+                let master = check(libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY))?;
+                let master = OwnedFd::from_raw_fd(master);
+                check(libc::grantpt(master.as_raw_fd()))?;
+                check(libc::unlockpt(master.as_raw_fd()))?;
+                let name = libc::ptsname(master.as_raw_fd());
+                if name.is_null() {
+                    return Err(io::Error::last_os_error());
+                }
+                let name = CStr::from_ptr(name).to_owned();
+                let slave = check(libc::open(name.as_ptr(), libc::O_RDWR | libc::O_NOCTTY))?;
+                let slave = OwnedFd::from_raw_fd(slave);
+                let mut termios = MaybeUninit::<libc::termios>::uninit();
+                check(libc::tcgetattr(slave.as_raw_fd(), termios.as_mut_ptr()))?;
+                let mut termios = termios.assume_init();
+                libc::cfmakeraw(&mut termios);
+                check(libc::cfsetspeed(&mut termios, libc::B9600))?;
+                check(libc::tcsetattr(slave.as_raw_fd(), libc::TCSANOW, &termios))?;
+                Ok(PseudoTerminal {
+                    master,
+                    slave,
+                    path: name.to_string_lossy().into_owned(),
+                })
+            }
+        }
+
+        /// return the path the host opens, e.g. /dev/pts/3
+        pub fn path(&self) -> &str {
+            &self.path
+        }
+
+        /// wait at most `timeout` for data from the host and read it
+        ///
+        /// returns 0 if no data arrived in time
+        pub fn read(&self, buf: &mut [u8], timeout: Duration) -> io::Result<usize> {
+            let mut poll_fd = libc::pollfd {
+                fd: self.master.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let timeout = timeout.as_millis().try_into().unwrap_or(libc::c_int::MAX);
+            // SAFETY: a single valid pollfd is passed
+            if check(unsafe { libc::poll(&mut poll_fd, 1, timeout) })? == 0 {
+                return Ok(0);
+            }
+            // SAFETY: the buffer is valid for its length
+            let count =
+                unsafe { libc::read(self.master.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+            if count < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(count as usize)
+        }
+
+        /// send all bytes of `data` to the host
+        pub fn write_all(&self, mut data: &[u8]) -> io::Result<()> {
+            while !data.is_empty() {
+                // SAFETY: the buffer is valid for its length
+                let count = unsafe {
+                    libc::write(self.master.as_raw_fd(), data.as_ptr().cast(), data.len())
+                };
+                if count < 0 {
+                    let err = io::Error::last_os_error();
+                    if err.kind() == io::ErrorKind::Interrupted {
+                        continue;
+                    }
+                    return Err(err);
+                }
+                data = &data[count as usize..];
+            }
+            Ok(())
+        }
+
+        /// return the baud rate the host set
+        ///
+        /// the speed is read with termios2, which also reports speeds set as arbitrary rate
+        #[cfg(target_os = "linux")]
+        pub fn baud_rate(&self) -> io::Result<u32> {
+            let mut termios = MaybeUninit::<libc::termios2>::uninit();
+            // SAFETY: the termios2 struct is only used after the ioctl initialized it
+            unsafe {
+                check(libc::ioctl(
+                    self.slave.as_raw_fd(),
+                    libc::TCGETS2,
+                    termios.as_mut_ptr(),
+                ))?;
+                Ok(termios.assume_init().c_ospeed)
+            }
+        }
+
+        /// return the baud rate the host set
+        ///
+        /// the BSDs (including macOS) use the baud rate itself as speed value
+        #[cfg(not(target_os = "linux"))]
+        pub fn baud_rate(&self) -> io::Result<u32> {
+            let mut termios = MaybeUninit::<libc::termios>::uninit();
+            // SAFETY: the termios struct is only used after tcgetattr initialized it
+            let speed = unsafe {
+                check(libc::tcgetattr(
+                    self.slave.as_raw_fd(),
+                    termios.as_mut_ptr(),
+                ))?;
+                libc::cfgetospeed(&termios.assume_init())
+            };
+            Ok(speed as u32)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_barcode() {
+        assert_eq!(
+            parse_barcode("51:hello"),
+            Ok(ScriptedBarcode {
+                code_id: Some(0x51),
+                data: b"hello".to_vec(),
+            })
+        );
+        // only two hex digits in front of the colon are a code ID
+        assert_eq!(parse_barcode("https://example.com").unwrap().code_id, None);
+        assert_eq!(parse_barcode("CODE39").unwrap().code_id_or(0x62), 0x62);
+        assert!(parse_barcode("xy:data").is_err());
+        assert_eq!(parse_code_id("0x6A"), Ok(0x6A));
+        assert!(parse_code_id("100").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_replace_link() -> CliResult<()> {
+        let dir = std::env::temp_dir().join(format!("waveshare-link-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let link = dir.join("ttyScanner");
+        // a new link is created and a link from an earlier run is replaced
+        replace_link(&link, "/dev/pts/1")?;
+        replace_link(&link, "/dev/pts/2")?;
+        assert_eq!(std::fs::read_link(&link)?, PathBuf::from("/dev/pts/2"));
+        // a regular file is kept
+        let file = dir.join("profile.toml");
+        std::fs::write(&file, "mode = 1")?;
+        assert!(replace_link(&file, "/dev/pts/2").is_err());
+        assert_eq!(std::fs::read_to_string(&file)?, "mode = 1");
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_virtual_scanner() -> CliResult<()> {
+        use waveshare_barcodescanner::{Barcode, interface::BarcodeScanner};
+
+        let terminal = pty::PseudoTerminal::open()?;
+        let path = terminal.path().to_string();
+        let (sender, typed) = mpsc::channel();
+        std::thread::spawn(move || {
+            if let Err(err) = serve(&terminal, SimulatedScanner::new(), &typed, 0x6A) {
+                eprintln!("error: {}", err);
+            }
+        });
+
+        let mut scanner = BarcodeScanner::new(&path)?;
+        assert_eq!(scanner.get_hw_version()?, "V1.40");
+        // the emulator follows the speed of the host
+        scanner.set_baud_rate(115200)?;
+        assert_eq!(scanner.get_baud_rate()?, 115200);
+        // manual mode, the typed barcode is sent right away
+        sender.send(parse_barcode("51:hello")?)?;
+        assert!(matches!(
+            scanner.read_barcode()?.map(|scan| scan.barcode),
+            Some(Barcode::QR(data)) if data == "hello"
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,301 @@
+use std::time::Duration;
+
+use serial2::{CharSize, FlowControl, Parity, SerialPort, Settings, StopBits};
+
+use tracing::debug;
+
+use crate::{Error, Result, interface::BarcodeScanner, retry::RetryPolicy, transport::Transport};
+
+/// baud rates tried by auto-detection, ordered by how common they are
+pub const DETECT_BAUD_RATES: [u32; 9] =
+    [9600, 115200, 57600, 38400, 19200, 14400, 4800, 2400, 1200];
+
+/// barcode result options (register 0x0060) written when the barcode scanner is opened
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// barcodes are sent as plain text with code ID, terminated by CR (default)
+    Text,
+    /// barcodes are sent in scan frames with a length and a checksum (protocol output mode)
+    Protocol,
+    /// the barcode result options of the device are not changed
+    ///
+    /// the device needs to be configured for plain text output with code ID and CR as end
+    /// character, unless verification is enabled which also detects protocol output mode
+    Unchanged,
+}
+
+/// builder to open a barcode scanner with custom options
+///
+/// The defaults match [`BarcodeScanner::new`]: 9600 baud, a read timeout of 100ms, a scan
+/// timeout of 5s and plain text output.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use anyhow::Result;
+/// use waveshare_barcodescanner::{builder::OutputFormat, interface::BarcodeScanner};
+///
+/// fn main() -> Result<()> {
+///     let mut scanner = BarcodeScanner::builder()
+///         .baud_rate(115200)
+///         .scan_timeout(Duration::from_secs(10))
+///         .output_format(OutputFormat::Unchanged)
+///         .verify(true)
+///         .open("/dev/serial0")?;
+///     println!("hardware version: {}", scanner.get_hw_version()?);
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct BarcodeScannerBuilder {
+    /// speed of the serial port
+    baud_rate: u32,
+    /// maximum time to wait for the next byte of a reply
+    read_timeout: Duration,
+    /// time to wait for a barcode in read_barcode
+    scan_timeout: Duration,
+    /// barcode result options written on open
+    output_format: OutputFormat,
+    /// read back the barcode result options on open
+    verify: bool,
+    /// policy for repeating failed command exchanges
+    retry_policy: RetryPolicy,
+}
+
+impl Default for BarcodeScannerBuilder {
+    fn default() -> Self {
+        BarcodeScannerBuilder {
+            baud_rate: 9600,
+            read_timeout: Duration::from_millis(100),
+            scan_timeout: Duration::from_secs(5),
+            output_format: OutputFormat::Text,
+            verify: false,
+            retry_policy: RetryPolicy::none(),
+        }
+    }
+}
+
+impl BarcodeScannerBuilder {
+    /// create a builder with the default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// set the speed of the serial port, it has to match the setting of the device
+    pub fn baud_rate(mut self, baud_rate: u32) -> Self {
+        self.baud_rate = baud_rate;
+        self
+    }
+
+    /// set the maximum time to wait for the next byte of a reply
+    pub fn read_timeout(mut self, read_timeout: Duration) -> Self {
+        self.read_timeout = read_timeout;
+        self
+    }
+
+    /// set the time [`BarcodeScanner::read_barcode`] waits for a barcode
+    ///
+    /// # Note
+    ///
+    /// Only the host side is configured, the scan timeout of the device is not changed. Use
+    /// [`BarcodeScanner::set_scan_timeout`] to change both.
+    pub fn scan_timeout(mut self, scan_timeout: Duration) -> Self {
+        self.scan_timeout = scan_timeout;
+        self
+    }
+
+    /// set the barcode result options written when opening the barcode scanner
+    pub fn output_format(mut self, output_format: OutputFormat) -> Self {
+        self.output_format = output_format;
+        self
+    }
+
+    /// read back the barcode result options after opening the barcode scanner
+    ///
+    /// fails with [`crate::Error::ConfigurationMismatch`] when the device did not take the
+    /// written options, or with [`crate::Error::InvalidData`] when unchanged options can not
+    /// be decoded by this crate
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// set the policy for repeating failed command exchanges, by default commands are sent once
+    ///
+    /// see [`RetryPolicy`]
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// open the serial port (8 data bits, no parity, 1 stop bit) and initialize the barcode scanner
+    ///
+    /// # Arguments
+    ///
+    /// * `serial_port_name` the device name of the serial port to open
+    pub fn open(&self, serial_port_name: &str) -> Result<BarcodeScanner<SerialPort>> {
+        let port = self.open_serial_port(serial_port_name)?;
+        self.with_transport(port)
+    }
+
+    /// initialize the barcode scanner on an already opened transport
+    ///
+    /// the baud rate is not applied, but the read timeout is set on the transport
+    ///
+    /// # Arguments
+    ///
+    /// * `transport` the connection to the barcode scanner
+    pub fn with_transport<T: Transport>(&self, mut transport: T) -> Result<BarcodeScanner<T>> {
+        transport.set_read_timeout(self.read_timeout)?;
+        BarcodeScanner::initialize(
+            transport,
+            self.scan_timeout,
+            self.output_format,
+            self.verify,
+            self.retry_policy.clone(),
+        )
+    }
+
+    /// open the serial port at whichever baud rate the barcode scanner answers on
+    ///
+    /// see [`BarcodeScannerBuilder::detect_with_transport`]
+    ///
+    /// # Arguments
+    ///
+    /// * `serial_port_name` the device name of the serial port to open
+    pub fn detect(&self, serial_port_name: &str) -> Result<BarcodeScanner<SerialPort>> {
+        let port = self.open_serial_port(serial_port_name)?;
+        self.detect_with_transport(port)
+    }
+
+    /// find the baud rate the barcode scanner answers on and initialize it
+    ///
+    /// The configured baud rate is tried first, followed by [`DETECT_BAUD_RATES`]. Each rate is
+    /// probed by reading the hardware version, which does not change any setting of the device.
+    /// The barcode scanner is initialized once it answered.
+    ///
+    /// # Arguments
+    ///
+    /// * `transport` the connection to the barcode scanner, supporting baud rate changes
+    ///
+    /// # Returns
+    ///
+    /// * the initialized barcode scanner, [`Error::Timeout`] if none of the rates worked
+    pub fn detect_with_transport<T: Transport>(
+        &self,
+        mut transport: T,
+    ) -> Result<BarcodeScanner<T>> {
+        transport.set_read_timeout(self.read_timeout)?;
+        let candidates = std::iter::once(self.baud_rate).chain(
+            DETECT_BAUD_RATES
+                .into_iter()
+                .filter(|&rate| rate != self.baud_rate),
+        );
+        for baud_rate in candidates {
+            debug!("probing {} baud", baud_rate);
+            transport.set_baud_rate(baud_rate)?;
+            // no settings are written until the device answered
+            let mut probe = BarcodeScanner::initialize(
+                transport,
+                self.scan_timeout,
+                OutputFormat::Unchanged,
+                false,
+                RetryPolicy::none(),
+            )?;
+            let result = probe.get_hw_version();
+            transport = probe.into_transport();
+            match result {
+                Ok(version) => {
+                    debug!("barcode scanner {} answered at {} baud", version, baud_rate);
+                    return self.clone().baud_rate(baud_rate).with_transport(transport);
+                }
+                // anything else than a transport failure means the speed does not match
+                Err(Error::Io(err)) => return Err(Error::Io(err)),
+                Err(err) => debug!("no valid reply at {} baud: {}", baud_rate, err),
+            }
+        }
+        Err(Error::Timeout)
+    }
+
+    /// open the serial port with the configured baud rate, 8 data bits, no parity, 1 stop bit
+    fn open_serial_port(&self, serial_port_name: &str) -> Result<SerialPort> {
+        let baud_rate = self.baud_rate;
+        let port = SerialPort::open(serial_port_name, |mut settings: Settings| {
+            settings.set_raw();
+            settings.set_baud_rate(baud_rate)?;
+            settings.set_char_size(CharSize::Bits8);
+            settings.set_stop_bits(StopBits::One);
+            settings.set_parity(Parity::None);
+            settings.set_flow_control(FlowControl::None);
+            Ok(settings)
+        })?;
+        Ok(port)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Barcode, simulator::SimulatedScanner};
+
+    #[test]
+    fn test_unchanged_output_format() -> Result<()> {
+        let mut simulator = SimulatedScanner::new();
+        simulator.set_register(0x0060, 0x84);
+        simulator.queue_barcode(0x62, b"CODE39")?;
+        let mut scanner = BarcodeScanner::builder()
+            .read_timeout(Duration::from_millis(10))
+            .output_format(OutputFormat::Unchanged)
+            .verify(true)
+            .with_transport(simulator)?;
+        assert_eq!(
+            scanner.transport().read_timeout()?,
+            Duration::from_millis(10)
+        );
+        assert!(scanner.protocol_output());
+        scanner.start_scan()?;
+        match scanner.read_barcode()?.map(|scan| scan.barcode) {
+            Some(Barcode::Code39(data)) => assert_eq!(data, "CODE39"),
+            _ => panic!("expected a Code39 barcode"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_and_set_baud_rate() -> Result<()> {
+        let mut simulator = SimulatedScanner::new();
+        simulator.set_register(0x002A, 0x34);
+        simulator.set_register(0x002B, 0x00);
+        let builder = BarcodeScanner::builder().read_timeout(Duration::from_millis(10));
+        let mut scanner = builder.detect_with_transport(simulator)?;
+        assert_eq!(scanner.get_sw_version()?, "V1.40");
+        scanner.set_baud_rate(115200)?;
+        assert_eq!(scanner.transport().baud_rate(), Some(115200));
+        assert_eq!(scanner.get_hw_version()?, "V1.40");
+        assert!(matches!(
+            scanner.set_baud_rate(300),
+            Err(Error::InvalidArgument(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify() -> Result<()> {
+        let scanner = BarcodeScanner::builder()
+            .output_format(OutputFormat::Protocol)
+            .verify(true)
+            .with_transport(SimulatedScanner::new())?;
+        assert_eq!(scanner.transport().register(0x0060), 0x84);
+        // factory default options do not include the code ID
+        assert!(matches!(
+            BarcodeScanner::builder()
+                .output_format(OutputFormat::Unchanged)
+                .verify(true)
+                .with_transport(SimulatedScanner::new()),
+            Err(Error::InvalidData(_))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,570 @@
+//! capture and replay of the traffic between host and barcode scanner
+//!
+//! [`RecordingTransport`] wraps another transport and writes every byte sent and received to a
+//! capture, [`ReplayTransport`] plays a capture back to a [`crate::interface::BarcodeScanner`].
+//! A capture of a field failure thereby becomes a unit test which fails deterministically.
+//!
+//! # Format
+//!
+//! A capture is a text file with one JSON object per line (JSONL):
+//!
+//! ```text
+//! {"time_us":823,"event":"tx","data":"7e00070100e10122c2"}
+//! {"time_us":829,"event":"rx","data":"0200"}
+//! {"time_us":834,"event":"rx","data":"00018c6335"}
+//! {"time_us":21914,"event":"timeout"}
+//! ```
+//!
+//! * `time_us` microseconds since the recording started
+//! * `event` `tx` for bytes written to the scanner, `rx` for bytes read from the scanner,
+//!   `timeout` for a read which timed out without data
+//! * `data` the bytes as hex string (`tx` and `rx` only)
+//!
+//! Only bytes actually read by the host are recorded, data dropped with
+//! [`Transport::discard_buffers`] does not appear in the capture.
+
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use tracing::debug;
+
+use crate::{Error, Result, transport::Transport};
+
+/// a single event of a capture
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaptureEvent {
+    /// bytes written to the barcode scanner
+    Sent(Vec<u8>),
+    /// bytes read from the barcode scanner
+    Received(Vec<u8>),
+    /// a read timed out without data
+    TimedOut,
+}
+
+/// an event of a capture together with the time it happened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureRecord {
+    /// time since the recording started
+    pub time: Duration,
+    /// what happened on the line
+    pub event: CaptureEvent,
+}
+
+impl CaptureRecord {
+    /// format the record as a line of a capture (without line break)
+    pub fn to_line(&self) -> String {
+        let time_us = self.time.as_micros();
+        match &self.event {
+            CaptureEvent::Sent(data) => {
+                format!(
+                    r#"{{"time_us":{},"event":"tx","data":"{}"}}"#,
+                    time_us,
+                    hex(data)
+                )
+            }
+            CaptureEvent::Received(data) => {
+                format!(
+                    r#"{{"time_us":{},"event":"rx","data":"{}"}}"#,
+                    time_us,
+                    hex(data)
+                )
+            }
+            CaptureEvent::TimedOut => format!(r#"{{"time_us":{},"event":"timeout"}}"#, time_us),
+        }
+    }
+
+    /// parse a line of a capture
+    pub fn parse_line(line: &str) -> Result<Self> {
+        let mut time_us: Option<u64> = None;
+        let mut event: Option<String> = None;
+        let mut data: Option<Vec<u8>> = None;
+        for (key, value) in parse_object(line)? {
+            match key {
+                "time_us" => {
+                    time_us = Some(value.parse().map_err(|_| {
+                        Error::InvalidArgument(format!("invalid time_us '{}'", value))
+                    })?)
+                }
+                "event" => event = Some(value.to_string()),
+                "data" => data = Some(parse_hex(value)?),
+                // unknown keys are ignored, e.g. comments added to a bug report
+                _ => {}
+            }
+        }
+        let time_us =
+            time_us.ok_or_else(|| Error::InvalidArgument(String::from("time_us missing")))?;
+        let event = match (event.as_deref(), data) {
+            (Some("tx"), Some(data)) => CaptureEvent::Sent(data),
+            (Some("rx"), Some(data)) => CaptureEvent::Received(data),
+            (Some("timeout"), _) => CaptureEvent::TimedOut,
+            (Some(event), _) => {
+                return Err(Error::InvalidArgument(format!(
+                    "invalid event '{}' or data missing",
+                    event
+                )));
+            }
+            (None, _) => return Err(Error::InvalidArgument(String::from("event missing"))),
+        };
+        Ok(CaptureRecord {
+            time: Duration::from_micros(time_us),
+            event,
+        })
+    }
+}
+
+/// parse all records of a capture, empty lines are skipped
+pub fn parse_capture(text: &str) -> Result<Vec<CaptureRecord>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            CaptureRecord::parse_line(line).map_err(|err| match err {
+                Error::InvalidArgument(message) => {
+                    Error::InvalidArgument(format!("capture line {}: {}", index + 1, message))
+                }
+                err => err,
+            })
+        })
+        .collect()
+}
+
+/// transport wrapper writing the traffic of another transport to a capture
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use serial2::SerialPort;
+/// use waveshare_barcodescanner::{capture::RecordingTransport, interface::BarcodeScanner};
+///
+/// fn main() -> Result<()> {
+///     let port = SerialPort::open("/dev/serial0", 9600)?;
+///     let transport = RecordingTransport::create(port, "scanner.jsonl")?;
+///     let mut scanner = BarcodeScanner::with_transport(transport)?;
+///     scanner.start_scan()?;
+///     println!("{:?}", scanner.read_barcode()?);
+///     Ok(())
+/// }
+/// ```
+pub struct RecordingTransport<T: Transport, W: Write> {
+    /// the transport whose traffic is recorded
+    inner: T,
+    /// receives the lines of the capture
+    output: W,
+    /// start of the recording
+    start: Instant,
+}
+
+impl<T: Transport> RecordingTransport<T, BufWriter<File>> {
+    /// record the traffic of `inner` to the file at `path`, which is created or truncated
+    pub fn create<P: AsRef<Path>>(inner: T, path: P) -> Result<Self> {
+        Ok(RecordingTransport::new(
+            inner,
+            BufWriter::new(File::create(path)?),
+        ))
+    }
+}
+
+impl<T: Transport, W: Write> RecordingTransport<T, W> {
+    /// record the traffic of `inner` to `output`
+    pub fn new(inner: T, output: W) -> Self {
+        RecordingTransport {
+            inner,
+            output,
+            start: Instant::now(),
+        }
+    }
+
+    /// return the transport whose traffic is recorded
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// stop recording and return the transport and the capture output
+    pub fn into_parts(mut self) -> std::io::Result<(T, W)> {
+        self.output.flush()?;
+        Ok((self.inner, self.output))
+    }
+
+    /// append an event to the capture
+    ///
+    /// every record is flushed, so the capture is complete even if the program crashes
+    fn record(&mut self, event: CaptureEvent) -> std::io::Result<()> {
+        let record = CaptureRecord {
+            time: self.start.elapsed(),
+            event,
+        };
+        writeln!(self.output, "{}", record.to_line())?;
+        self.output.flush()
+    }
+}
+
+impl<T: Transport, W: Write> Transport for RecordingTransport<T, W> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self.inner.read(buf) {
+            Ok(count) => {
+                if count > 0 {
+                    self.record(CaptureEvent::Received(buf[..count].to_vec()))?;
+                }
+                Ok(count)
+            }
+            Err(err) => {
+                if err.kind() == std::io::ErrorKind::TimedOut {
+                    self.record(CaptureEvent::TimedOut)?;
+                }
+                Err(err)
+            }
+        }
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        self.inner.write_all(buf)?;
+        self.record(CaptureEvent::Sent(buf.to_vec()))
+    }
+
+    fn set_read_timeout(&mut self, timeout: Duration) -> std::io::Result<()> {
+        self.inner.set_read_timeout(timeout)
+    }
+
+    fn read_timeout(&self) -> std::io::Result<Duration> {
+        self.inner.read_timeout()
+    }
+
+    fn discard_buffers(&mut self) -> std::io::Result<()> {
+        self.inner.discard_buffers()
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> std::io::Result<()> {
+        self.inner.set_baud_rate(baud_rate)
+    }
+}
+
+/// transport playing back a capture
+///
+/// Reads return the recorded data and timeouts in order. Writes have to match the next
+/// recorded `tx` event, otherwise the host no longer behaves as recorded and the write fails
+/// with an error of kind [`std::io::ErrorKind::InvalidData`]. Timeouts depend on the timing of
+/// the host: recorded timeouts still pending at a write are skipped, and reads beyond the
+/// recorded data time out, as on an idle line.
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use waveshare_barcodescanner::{capture::ReplayTransport, interface::BarcodeScanner};
+///
+/// fn main() -> Result<()> {
+///     let transport = ReplayTransport::load("scanner.jsonl")?;
+///     let mut scanner = BarcodeScanner::with_transport(transport)?;
+///     scanner.start_scan()?;
+///     println!("{:?}", scanner.read_barcode()?);
+///     Ok(())
+/// }
+/// ```
+pub struct ReplayTransport {
+    /// events not played back yet
+    events: VecDeque<CaptureEvent>,
+    /// time a read waits when there is no data to return
+    read_timeout: Duration,
+}
+
+impl ReplayTransport {
+    /// play back the given records
+    pub fn from_records(records: Vec<CaptureRecord>) -> Self {
+        ReplayTransport {
+            events: records.into_iter().map(|record| record.event).collect(),
+            read_timeout: Duration::from_millis(100),
+        }
+    }
+
+    /// play back a capture given as text
+    pub fn parse(text: &str) -> Result<Self> {
+        Ok(ReplayTransport::from_records(parse_capture(text)?))
+    }
+
+    /// play back the capture in the file at `path`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        ReplayTransport::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// return true if all sent and received data of the capture has been played back
+    pub fn is_finished(&self) -> bool {
+        self.events
+            .iter()
+            .all(|event| *event == CaptureEvent::TimedOut)
+    }
+
+    /// wait for the read timeout and report it, the line is idle
+    fn time_out(&self) -> std::io::Result<usize> {
+        std::thread::sleep(self.read_timeout);
+        Err(std::io::ErrorKind::TimedOut.into())
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            match self.events.front_mut() {
+                Some(CaptureEvent::Received(data)) if data.is_empty() => {
+                    self.events.pop_front();
+                }
+                Some(CaptureEvent::Received(data)) => {
+                    let count = buf.len().min(data.len());
+                    for (target, source) in buf.iter_mut().zip(data.drain(..count)) {
+                        *target = source;
+                    }
+                    if data.is_empty() {
+                        self.events.pop_front();
+                    }
+                    return Ok(count);
+                }
+                Some(CaptureEvent::TimedOut) => {
+                    self.events.pop_front();
+                    return self.time_out();
+                }
+                // the host has to write first
+                Some(CaptureEvent::Sent(_)) | None => return self.time_out(),
+            }
+        }
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        while self.events.front() == Some(&CaptureEvent::TimedOut) {
+            self.events.pop_front();
+        }
+        match self.events.pop_front() {
+            Some(CaptureEvent::Sent(data)) if data == buf => Ok(()),
+            event => {
+                let expected = match event {
+                    Some(CaptureEvent::Sent(data)) => format!("write of {:02X?}", data),
+                    Some(CaptureEvent::Received(data)) => format!("read of {:02X?}", data),
+                    _ => String::from("end of capture"),
+                };
+                debug!(
+                    "REPLAY diverged, {:02X?} written, expected {}",
+                    buf, expected
+                );
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "replay diverged: {:02X?} written, capture expects {}",
+                        buf, expected
+                    ),
+                ))
+            }
+        }
+    }
+
+    fn set_read_timeout(&mut self, timeout: Duration) -> std::io::Result<()> {
+        self.read_timeout = timeout;
+        Ok(())
+    }
+
+    fn read_timeout(&self) -> std::io::Result<Duration> {
+        Ok(self.read_timeout)
+    }
+
+    fn discard_buffers(&mut self) -> std::io::Result<()> {
+        // discarded data was never read, so it is not part of the capture
+        Ok(())
+    }
+
+    fn set_baud_rate(&mut self, _baud_rate: u32) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// format bytes as lower case hex without separators
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// parse bytes given as hex, separating spaces are allowed
+fn parse_hex(text: &str) -> Result<Vec<u8>> {
+    let digits: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if digits.len() % 2 != 0 {
+        return Err(Error::InvalidArgument(format!(
+            "odd number of hex digits in '{}'",
+            text
+        )));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let byte: String = pair.iter().collect();
+            u8::from_str_radix(&byte, 16)
+                .map_err(|_| Error::InvalidArgument(format!("invalid hex byte '{}'", byte)))
+        })
+        .collect()
+}
+
+/// split a flat JSON object with string and number values into its keys and values
+///
+/// string values must not contain escapes, which a capture never does
+fn parse_object(line: &str) -> Result<Vec<(&str, &str)>> {
+    let invalid = || Error::InvalidArgument(format!("invalid capture record '{}'", line));
+    let mut rest = line
+        .trim()
+        .strip_prefix('{')
+        .and_then(|rest| rest.strip_suffix('}'))
+        .ok_or_else(invalid)?
+        .trim();
+    let mut pairs = Vec::new();
+    while !rest.is_empty() {
+        let (key, after_key) = rest
+            .strip_prefix('"')
+            .and_then(|rest| rest.split_once('"'))
+            .ok_or_else(invalid)?;
+        let after_colon = after_key
+            .trim_start()
+            .strip_prefix(':')
+            .ok_or_else(invalid)?
+            .trim_start();
+        let (value, after_value) = match after_colon.strip_prefix('"') {
+            Some(string) => string.split_once('"').ok_or_else(invalid)?,
+            None => {
+                let end = after_colon.find(',').unwrap_or(after_colon.len());
+                (after_colon[..end].trim_end(), &after_colon[end..])
+            }
+        };
+        pairs.push((key, value));
+        let after_value = after_value.trim_start();
+        rest = match after_value.strip_prefix(',') {
+            Some(rest) => rest.trim_start(),
+            None if after_value.is_empty() => after_value,
+            None => return Err(invalid()),
+        };
+    }
+    Ok(pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Barcode, IlluminationMode, OperationMode, TargetLightMode, interface::BarcodeScanner,
+        simulator::SimulatedScanner,
+    };
+
+    /// session run against the simulator and played back from the capture
+    fn session<T: Transport>(scanner: &mut BarcodeScanner<T>) -> Result<Option<Barcode>> {
+        assert_eq!(scanner.get_hw_version()?, "V1.40");
+        scanner.set_mode(
+            false,
+            false,
+            TargetLightMode::Standard,
+            IlluminationMode::Standard,
+            OperationMode::Command,
+        )?;
+        scanner.start_scan()?;
+        Ok(scanner.read_barcode()?.map(|scan| scan.barcode))
+    }
+
+    /// record the session and return the capture
+    fn record() -> Result<String> {
+        let mut simulator = SimulatedScanner::new();
+        simulator.set_read_timeout(Duration::from_millis(10))?;
+        simulator.queue_barcode(0x62, b"CODE39")?;
+        let transport = RecordingTransport::new(simulator, Vec::new());
+        let mut scanner = BarcodeScanner::with_transport(transport)?;
+        assert!(matches!(session(&mut scanner)?, Some(Barcode::Code39(data)) if data == "CODE39"));
+        let (_, output) = scanner.into_transport().into_parts()?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    fn replay(capture: &str) -> Result<BarcodeScanner<ReplayTransport>> {
+        let mut transport = ReplayTransport::parse(capture)?;
+        transport.set_read_timeout(Duration::from_millis(10))?;
+        BarcodeScanner::with_transport(transport)
+    }
+
+    #[test]
+    fn test_record_and_replay() -> Result<()> {
+        let capture = record()?;
+        let records = parse_capture(&capture)?;
+        assert!(matches!(records[0].event, CaptureEvent::Sent(_)));
+        let received: Vec<u8> = records
+            .iter()
+            .filter_map(|record| match &record.event {
+                CaptureEvent::Received(data) => Some(data.clone()),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        assert!(received.windows(6).any(|data| data == b"CODE39"));
+        assert!(records.windows(2).all(|pair| pair[0].time <= pair[1].time));
+
+        let mut scanner = replay(&capture)?;
+        assert!(matches!(session(&mut scanner)?, Some(Barcode::Code39(data)) if data == "CODE39"));
+        assert!(scanner.transport().is_finished());
+        Ok(())
+    }
+
+    #[test]
+    fn test_replay_diverged() -> Result<()> {
+        let capture = record()?;
+        let mut scanner = replay(&capture)?;
+        // the capture starts with reading the hardware version
+        assert!(matches!(
+            scanner.get_sw_version(),
+            Err(Error::Io(err)) if err.kind() == std::io::ErrorKind::InvalidData
+        ));
+        // a barcode with an unknown code ID is reproduced
+        let capture = capture.replace(r#""data":"62""#, r#""data":"7f""#);
+        let mut scanner = replay(&capture)?;
+        assert!(matches!(
+            session(&mut scanner)?,
+            Some(Barcode::Unknown(0x7F, data)) if data == "CODE39"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_capture_format() -> Result<()> {
+        let records = [
+            CaptureRecord {
+                time: Duration::from_micros(0),
+                event: CaptureEvent::Sent(vec![0x7E, 0x00, 0x07]),
+            },
+            CaptureRecord {
+                time: Duration::from_micros(2100),
+                event: CaptureEvent::Received(vec![0x02, 0x00, 0xAB]),
+            },
+            CaptureRecord {
+                time: Duration::from_micros(104350),
+                event: CaptureEvent::TimedOut,
+            },
+        ];
+        let lines: Vec<String> = records.iter().map(CaptureRecord::to_line).collect();
+        assert_eq!(lines[0], r#"{"time_us":0,"event":"tx","data":"7e0007"}"#);
+        assert_eq!(lines[2], r#"{"time_us":104350,"event":"timeout"}"#);
+        assert_eq!(parse_capture(&lines.join("\n"))?, records);
+        // whitespace, other key orders, separated hex and unknown keys are accepted
+        assert_eq!(
+            CaptureRecord::parse_line(
+                r#" { "data" : "02 00 ab", "note": "reply", "event":"rx", "time_us" : 2100 } "#
+            )?,
+            records[1]
+        );
+        for line in [
+            r#"{"time_us":0,"event":"tx"}"#,
+            r#"{"time_us":0,"event":"rx","data":"7e0"}"#,
+            r#"{"time_us":-1,"event":"timeout"}"#,
+            r#"{"event":"timeout"}"#,
+            r#"{"time_us":0,"event":"tx","data":"7e00" "#,
+            r#"{"time_us":0 "event":"timeout"}"#,
+        ] {
+            assert!(CaptureRecord::parse_line(line).is_err(), "{}", line);
+        }
+        assert!(matches!(
+            parse_capture("\n{}"),
+            Err(Error::InvalidArgument(message)) if message.starts_with("capture line 2:")
+        ));
+        Ok(())
+    }
+}
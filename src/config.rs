@@ -0,0 +1,355 @@
+use std::{fmt::Display, time::Duration};
+
+use tracing::debug;
+
+use crate::{
+    Barcodes, Error, Result, ScanArea, ScannerMode, Symbology, SymbologyStatus,
+    interface::BarcodeScanner,
+    protocol::{decode_baud_rate, decode_scan_timeout, decode_symbology_status},
+    registers::{
+        BAUD_RATE, FACTORY_RESET, HW_VERSION, MODE, RESULT_OPTIONS, Register, SCAN_TIMEOUT,
+        SCAN_TRIGGER, SW_DATE, SW_VERSION, ScanAreaAndBarcodes,
+    },
+    transport::Transport,
+};
+
+/// number of registers contained in a configuration snapshot (starting at 0x0000)
+pub const CONFIG_SIZE: usize = 256;
+
+/// registers which are never written back when restoring a configuration
+///
+/// * 0x0002 scan trigger
+/// * 0x002A-0x002B baud rate (would break the connection)
+/// * 0x0060 barcode result options (managed when opening the barcode scanner)
+/// * 0x00D9 factory reset
+/// * 0x00E1-0x00E5 versions and software date (read only)
+pub const EXCLUDED_REGISTERS: [u16; 10] = [
+    SCAN_TRIGGER,
+    BAUD_RATE,
+    BAUD_RATE + 1,
+    RESULT_OPTIONS,
+    FACTORY_RESET,
+    HW_VERSION,
+    SW_VERSION,
+    SW_DATE,
+    SW_DATE + 1,
+    SW_DATE + 2,
+];
+
+/// snapshot of the complete register block of a barcode scanner
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use waveshare_barcodescanner::interface::BarcodeScanner;
+///
+/// fn main() -> Result<()> {
+///     let mut old_scanner = BarcodeScanner::new("/dev/ttyUSB0")?;
+///     let config = old_scanner.read_config()?;
+///     let mut new_scanner = BarcodeScanner::new("/dev/ttyUSB1")?;
+///     println!("{}", new_scanner.read_config()?.diff(&config));
+///     new_scanner.restore_config(&config, true)?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScannerConfig {
+    /// register values, starting at address 0x0000
+    registers: Vec<u8>,
+}
+
+impl ScannerConfig {
+    /// create a snapshot from register values starting at address 0x0000
+    ///
+    /// # Arguments
+    ///
+    /// * `registers` the values of the registers 0x0000 to 0x00FF
+    pub fn from_registers(registers: Vec<u8>) -> Result<Self> {
+        if registers.len() != CONFIG_SIZE {
+            return Err(Error::UnexpectedLength {
+                expected: CONFIG_SIZE,
+                received: registers.len(),
+            });
+        }
+        Ok(ScannerConfig { registers })
+    }
+
+    /// return the register values, starting at address 0x0000
+    pub fn registers(&self) -> &[u8] {
+        &self.registers
+    }
+
+    /// return the value of a single register, None if it is not part of the snapshot
+    pub fn register(&self, address: u16) -> Option<u8> {
+        self.registers.get(address as usize).copied()
+    }
+
+    /// return the typed value of a register
+    pub fn typed_register<R: Register>(&self) -> R {
+        R::decode(self.registers[R::ADDRESS as usize])
+    }
+
+    /// return the mode of operation and light/buzzer parameters
+    pub fn mode(&self) -> ScannerMode {
+        self.typed_register()
+    }
+
+    /// return the scanning area and barcodes allowed
+    pub fn scan_area_and_barcodes(&self) -> (ScanArea, Barcodes) {
+        let register: ScanAreaAndBarcodes = self.typed_register();
+        (register.scan_area, register.barcodes)
+    }
+
+    /// return the scan timeout (zero for no timeout)
+    pub fn scan_timeout(&self) -> Duration {
+        decode_scan_timeout(self.registers[SCAN_TIMEOUT as usize])
+    }
+
+    /// return the configured baud rate
+    pub fn baud_rate(&self) -> Result<u32> {
+        let address = BAUD_RATE as usize;
+        decode_baud_rate([self.registers[address], self.registers[address + 1]])
+    }
+
+    /// return if a barcode type is enabled, together with its length limits
+    pub fn symbology(&self, symbology: Symbology) -> SymbologyStatus {
+        let address = symbology.enable_address() as usize;
+        let length = if symbology.has_length_limits() { 3 } else { 1 };
+        // the register block always contains all barcode type registers
+        decode_symbology_status(symbology, &self.registers[address..address + length])
+            .expect("barcode type registers are part of the snapshot")
+    }
+
+    /// compare this snapshot to `other`, listing what needs to change to get to `other`
+    pub fn diff(&self, other: &ScannerConfig) -> ConfigDiff {
+        let registers: Vec<RegisterChange> = self
+            .registers
+            .iter()
+            .zip(other.registers.iter())
+            .enumerate()
+            .filter(|(_, (current, target))| current != target)
+            .map(|(address, (&current, &target))| RegisterChange {
+                address: address as u16,
+                current,
+                target,
+            })
+            .collect();
+        let mut settings: Vec<SettingChange> = Vec::new();
+        if self.mode() != other.mode() {
+            settings.push(SettingChange::new("mode", self.mode(), other.mode()));
+        }
+        if self.scan_area_and_barcodes() != other.scan_area_and_barcodes() {
+            settings.push(SettingChange::new(
+                "scan area and barcodes",
+                self.scan_area_and_barcodes(),
+                other.scan_area_and_barcodes(),
+            ));
+        }
+        if self.scan_timeout() != other.scan_timeout() {
+            settings.push(SettingChange::new(
+                "scan timeout",
+                self.scan_timeout(),
+                other.scan_timeout(),
+            ));
+        }
+        if self.baud_rate().ok() != other.baud_rate().ok() {
+            settings.push(SettingChange::new(
+                "baud rate",
+                self.baud_rate().ok(),
+                other.baud_rate().ok(),
+            ));
+        }
+        for symbology in Symbology::ALL {
+            if self.symbology(symbology) != other.symbology(symbology) {
+                settings.push(SettingChange::new(
+                    &format!("{:?}", symbology),
+                    self.symbology(symbology),
+                    other.symbology(symbology),
+                ));
+            }
+        }
+        ConfigDiff {
+            registers,
+            settings,
+        }
+    }
+}
+
+/// a register with different values in two snapshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterChange {
+    /// register address
+    pub address: u16,
+    /// value in the snapshot compared
+    pub current: u8,
+    /// value in the snapshot compared to
+    pub target: u8,
+}
+
+impl RegisterChange {
+    /// true if the register is written when restoring a configuration
+    pub fn is_restorable(&self) -> bool {
+        !EXCLUDED_REGISTERS.contains(&self.address)
+    }
+}
+
+/// a decoded setting with different values in two snapshots
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettingChange {
+    /// name of the setting
+    pub name: String,
+    /// value in the snapshot compared
+    pub current: String,
+    /// value in the snapshot compared to
+    pub target: String,
+}
+
+impl SettingChange {
+    /// describe a changed setting using the debug representation of its values
+    fn new<V: std::fmt::Debug>(name: &str, current: V, target: V) -> Self {
+        SettingChange {
+            name: String::from(name),
+            current: format!("{:?}", current),
+            target: format!("{:?}", target),
+        }
+    }
+}
+
+/// differences between two configuration snapshots
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigDiff {
+    /// registers with different values
+    pub registers: Vec<RegisterChange>,
+    /// decoded settings with different values
+    pub settings: Vec<SettingChange>,
+}
+
+impl ConfigDiff {
+    /// true if both snapshots are identical
+    pub fn is_empty(&self) -> bool {
+        self.registers.is_empty()
+    }
+}
+
+impl Display for ConfigDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no differences");
+        }
+        for change in &self.settings {
+            writeln!(
+                f,
+                "{}: {} -> {}",
+                change.name, change.current, change.target
+            )?;
+        }
+        for change in &self.registers {
+            writeln!(
+                f,
+                "register {:04X}: {:02X} -> {:02X}",
+                change.address, change.current, change.target
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Transport> BarcodeScanner<T> {
+    /// read the complete register block (0x0000 to 0x00FF) of the barcode scanner
+    pub fn read_config(&mut self) -> Result<ScannerConfig> {
+        let mut registers: Vec<u8> = vec![0x00; CONFIG_SIZE];
+        self.read_registers_into(MODE, &mut registers)?;
+        ScannerConfig::from_registers(registers)
+    }
+
+    /// write a configuration snapshot back to the barcode scanner
+    ///
+    /// Only registers which differ from the current configuration are written, the
+    /// [`EXCLUDED_REGISTERS`] are never written.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` the configuration to restore
+    /// * `save` if true the configuration is saved to flash afterwards
+    ///
+    /// # Returns
+    ///
+    /// * the registers which have been written
+    pub fn restore_config(
+        &mut self,
+        config: &ScannerConfig,
+        save: bool,
+    ) -> Result<Vec<RegisterChange>> {
+        let changes: Vec<RegisterChange> = self
+            .read_config()?
+            .diff(config)
+            .registers
+            .into_iter()
+            .filter(RegisterChange::is_restorable)
+            .collect();
+        // write consecutive registers with a single command
+        let mut start = 0;
+        while start < changes.len() {
+            let mut end = start + 1;
+            while end < changes.len() && changes[end].address == changes[end - 1].address + 1 {
+                end += 1;
+            }
+            let values: Vec<u8> = changes[start..end]
+                .iter()
+                .map(|change| change.target)
+                .collect();
+            debug!(
+                "restoring {} registers at {:04X}",
+                values.len(),
+                changes[start].address
+            );
+            self.send_write_command(changes[start].address, &values)?;
+            start = end;
+        }
+        if save {
+            self.save_to_flash()?;
+        }
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OperationMode, simulator::SimulatedScanner};
+
+    #[test]
+    fn test_diff_and_restore() -> Result<()> {
+        let mut old_scanner = BarcodeScanner::with_transport(SimulatedScanner::new())?;
+        old_scanner.allow_code93(true, Some(4), Some(20))?;
+        old_scanner.set_scan_timeout(Duration::from_secs(2))?;
+        old_scanner.transport_mut().set_register(0x0000, 0xD5);
+        let config = old_scanner.read_config()?;
+        assert_eq!(config.mode().operation_mode, OperationMode::Command);
+
+        let mut new_scanner = BarcodeScanner::with_transport(SimulatedScanner::new())?;
+        new_scanner.transport_mut().set_register(0x00E1, 0x8D);
+        let diff = new_scanner.read_config()?.diff(&config);
+        assert_eq!(
+            diff.settings
+                .iter()
+                .map(|change| change.name.as_str())
+                .collect::<Vec<&str>>(),
+            ["mode", "scan timeout", "Code93"]
+        );
+        let written = new_scanner.restore_config(&config, true)?;
+        assert_eq!(
+            written
+                .iter()
+                .map(|change| change.address)
+                .collect::<Vec<u16>>(),
+            [0x0000, 0x0006, 0x0039, 0x003A, 0x003B]
+        );
+        let simulator = new_scanner.transport();
+        assert_eq!(simulator.flash_register(0x003A), 4);
+        // read only registers are not restored
+        assert_eq!(simulator.register(0x00E1), 0x8D);
+        Ok(())
+    }
+}
@@ -1,8 +1,7 @@
-use anyhow::{Result, anyhow};
 use crc_any::CRC;
 use tracing::trace;
 
-use crate::IGNORED_CHECKSUM;
+use crate::{Error, IGNORED_CHECKSUM, Result};
 
 /// calculate the checksum of a command or reply packet
 /// 
@@ -21,7 +20,8 @@ pub fn calculate_crc(data: &[u8]) -> Result<u16> {
     crc_calc.digest(data);
     let crc = crc_calc.get_crc();
     trace!("CRC {:04X}", crc);
-    Ok(crc.try_into()?)
+    // the CRC is 16 bits wide, so the conversion is lossless
+    Ok(crc as u16)
 }
 
 pub fn verify_crc(data: &[u8], expected_checksum: u16) -> Result<()> {
@@ -32,11 +32,10 @@ pub fn verify_crc(data: &[u8], expected_checksum: u16) -> Result<()> {
     let calculated_checksum = calculate_crc(data)?;
     if expected_checksum != calculated_checksum {
         // checksum doesn't match
-        return Err(anyhow!(
-            "checksums don't match, expected {} received {}",
-            expected_checksum,
-            calculated_checksum
-        ));
+        return Err(Error::ChecksumMismatch {
+            expected: expected_checksum,
+            calculated: calculated_checksum,
+        });
     }
     Ok(())
 }
@@ -0,0 +1,55 @@
+use thiserror::Error;
+
+/// errors returned when communicating with the barcode scanner
+#[derive(Debug, Error)]
+pub enum Error {
+    /// the underlying transport reported an error
+    #[error("I/O error: {0}")]
+    Io(#[source] std::io::Error),
+    /// no (complete) reply was received from the barcode scanner in time
+    #[error("timeout waiting for data from the barcode scanner")]
+    Timeout,
+    /// a reply packet did not start with the expected header
+    #[error("invalid header received")]
+    BadHeader,
+    /// the checksum of a reply packet does not match its content
+    #[error("checksums don't match, expected {expected:04X} calculated {calculated:04X}")]
+    ChecksumMismatch { expected: u16, calculated: u16 },
+    /// the barcode scanner replied with a non-zero return code
+    #[error("barcode scanner indicates an unsuccessful operation, rc: {0}")]
+    DeviceRejected(u8),
+    /// the barcode scanner returned a different amount of data than requested
+    #[error("incorrect number of bytes read from device, expected {expected} received {received}")]
+    UnexpectedLength { expected: usize, received: usize },
+    /// the barcode scanner returned a value which can not be interpreted
+    #[error("invalid data received from device: {0}")]
+    InvalidData(String),
+    /// a register read back from the barcode scanner does not contain the value written
+    #[error("register {address:04X} contains {actual:02X}, expected {expected:02X}")]
+    ConfigurationMismatch {
+        address: u16,
+        expected: u8,
+        actual: u8,
+    },
+    /// the background reader thread is no longer running
+    #[error("the background reader is not running")]
+    ReaderStopped,
+    /// waiting for a barcode was cancelled using a [`crate::scan::CancelHandle`]
+    #[error("waiting for a barcode was cancelled")]
+    Cancelled,
+    /// a parameter passed in is out of range
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        match err.kind() {
+            std::io::ErrorKind::TimedOut => Error::Timeout,
+            _ => Error::Io(err),
+        }
+    }
+}
+
+/// result type used throughout this crate
+pub type Result<T> = std::result::Result<T, Error>;
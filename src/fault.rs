@@ -0,0 +1,503 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::Duration,
+};
+
+use tracing::debug;
+
+use crate::{
+    crc::calculate_crc,
+    protocol::{REPLY_HEADER, REPLY_OVERHEAD},
+    transport::Transport,
+};
+
+/// disturbance of the data received after a command, offsets count from the first byte
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// flip the bits of `mask` in the byte at `offset`
+    Corrupt { offset: usize, mask: u8 },
+    /// invalidate the checksum of the reply packet
+    CorruptChecksum,
+    /// replace the status of the reply packet, the checksum is recalculated
+    Status(u8),
+    /// lose `count` bytes starting at `offset`
+    Drop { offset: usize, count: usize },
+    /// receive the byte at `offset` twice
+    Duplicate { offset: usize },
+    /// receive the byte at `offset` after `delay`
+    Delay { offset: usize, delay: Duration },
+    /// lose all bytes from `offset` on, e.g. a partial frame
+    Truncate { offset: usize },
+}
+
+/// transport wrapper injecting faults into the data received from another transport
+///
+/// Faults are scheduled for the data received after a command, i.e. the reply and any barcode
+/// data following it. On the first read after the command the wrapper collects everything the
+/// inner transport delivers until its read times out, applies the faults and returns the result
+/// to the following reads. Delays longer than the read timeout make reads time out as on a
+/// real line. Commands without scheduled faults pass through unchanged.
+///
+/// # Examples
+///
+/// ```
+/// use anyhow::Result;
+/// use waveshare_barcodescanner::{
+///     Error,
+///     fault::{Fault, FaultyTransport},
+///     interface::BarcodeScanner,
+///     simulator::SimulatedScanner,
+/// };
+///
+/// fn main() -> Result<()> {
+///     let transport = FaultyTransport::new(SimulatedScanner::new());
+///     let mut scanner = BarcodeScanner::with_transport(transport)?;
+///     scanner.transport_mut().schedule(0, Fault::CorruptChecksum);
+///     assert!(matches!(
+///         scanner.get_hw_version(),
+///         Err(Error::ChecksumMismatch { .. })
+///     ));
+///     assert_eq!(scanner.get_hw_version()?, "V1.40");
+///     Ok(())
+/// }
+/// ```
+pub struct FaultyTransport<T: Transport> {
+    /// the transport whose data is disturbed
+    inner: T,
+    /// faults by the number of the command they apply to
+    schedule: BTreeMap<usize, Vec<Fault>>,
+    /// number of commands written so far
+    commands: usize,
+    /// faults of the last command, applied on the next read
+    armed: Vec<Fault>,
+    /// received data with the faults applied, with the delay in front of each byte
+    pending: VecDeque<(Duration, u8)>,
+}
+
+impl<T: Transport> FaultyTransport<T> {
+    /// wrap `inner` without any faults scheduled
+    pub fn new(inner: T) -> Self {
+        FaultyTransport {
+            inner,
+            schedule: BTreeMap::new(),
+            commands: 0,
+            armed: Vec::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// disturb the data received after a command
+    ///
+    /// `command` counts the commands from now on, 0 is the next command written. Several faults
+    /// can be scheduled for the same command.
+    pub fn schedule(&mut self, command: usize, fault: Fault) -> &mut Self {
+        self.schedule
+            .entry(self.commands + command)
+            .or_default()
+            .push(fault);
+        self
+    }
+
+    /// return the number of commands written so far
+    pub fn commands_sent(&self) -> usize {
+        self.commands
+    }
+
+    /// return true if scheduled faults have not been applied yet
+    pub fn faults_pending(&self) -> bool {
+        !self.schedule.is_empty() || !self.armed.is_empty()
+    }
+
+    /// return the transport whose data is disturbed
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// return the transport whose data is disturbed for modification
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// return the transport whose data is disturbed, pending data is lost
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// read the data of the last command from the inner transport and apply the faults
+    fn collect(&mut self) -> std::io::Result<()> {
+        let faults = std::mem::take(&mut self.armed);
+        let mut data: Vec<u8> = Vec::new();
+        let mut chunk = [0x00; 256];
+        loop {
+            match self.inner.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(count) => data.extend_from_slice(&chunk[..count]),
+                Err(err) if err.kind() == std::io::ErrorKind::TimedOut => break,
+                Err(err) => return Err(err),
+            }
+        }
+        debug!("FAULT applying {:?} to {:02X?}", faults, data);
+        self.pending.extend(apply_faults(data, &faults));
+        Ok(())
+    }
+}
+
+impl<T: Transport> Transport for FaultyTransport<T> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if !self.armed.is_empty() {
+            self.collect()?;
+        }
+        let Some((delay, _)) = self.pending.front_mut() else {
+            return self.inner.read(buf);
+        };
+        if !delay.is_zero() {
+            let read_timeout = self.inner.read_timeout()?;
+            if *delay > read_timeout {
+                *delay -= read_timeout;
+                std::thread::sleep(read_timeout);
+                return Err(std::io::ErrorKind::TimedOut.into());
+            }
+            std::thread::sleep(*delay);
+            *delay = Duration::ZERO;
+        }
+        let mut count = 0;
+        while count < buf.len() {
+            match self.pending.front() {
+                Some((delay, byte)) if delay.is_zero() => {
+                    buf[count] = *byte;
+                    count += 1;
+                    self.pending.pop_front();
+                }
+                _ => break,
+            }
+        }
+        Ok(count)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        self.inner.write_all(buf)?;
+        self.armed = self.schedule.remove(&self.commands).unwrap_or_default();
+        self.commands += 1;
+        Ok(())
+    }
+
+    fn set_read_timeout(&mut self, timeout: Duration) -> std::io::Result<()> {
+        self.inner.set_read_timeout(timeout)
+    }
+
+    fn read_timeout(&self) -> std::io::Result<Duration> {
+        self.inner.read_timeout()
+    }
+
+    fn discard_buffers(&mut self) -> std::io::Result<()> {
+        self.armed.clear();
+        self.pending.clear();
+        self.inner.discard_buffers()
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> std::io::Result<()> {
+        self.inner.set_baud_rate(baud_rate)
+    }
+}
+
+/// apply the faults to the data received after a command
+///
+/// returns the resulting bytes together with the delay in front of each byte
+fn apply_faults(mut data: Vec<u8>, faults: &[Fault]) -> Vec<(Duration, u8)> {
+    // faults on the reply packet as a whole
+    let reply_length = reply_length(&data);
+    for fault in faults {
+        match (fault, reply_length) {
+            (Fault::CorruptChecksum, Some(length)) => data[length - 1] ^= 0xFF,
+            (Fault::Status(status), Some(length)) => {
+                data[2] = *status;
+                if let Ok(checksum) = calculate_crc(&data[2..length - 2]) {
+                    data[length - 2..length].copy_from_slice(&checksum.to_be_bytes());
+                }
+            }
+            _ => {}
+        }
+    }
+    // faults on single bytes
+    let mut result: Vec<(Duration, u8)> = Vec::with_capacity(data.len());
+    let mut delay = Duration::ZERO;
+    for (offset, mut byte) in data.into_iter().enumerate() {
+        let mut dropped = false;
+        let mut duplicated = false;
+        for fault in faults {
+            match *fault {
+                Fault::Corrupt { offset: at, mask } if at == offset => byte ^= mask,
+                Fault::Drop { offset: at, count } if (at..at + count).contains(&offset) => {
+                    dropped = true;
+                }
+                Fault::Duplicate { offset: at } if at == offset => duplicated = true,
+                Fault::Delay {
+                    offset: at,
+                    delay: extra,
+                } if at == offset => delay += extra,
+                Fault::Truncate { offset: at } if offset >= at => return result,
+                _ => {}
+            }
+        }
+        if dropped {
+            continue;
+        }
+        result.push((std::mem::take(&mut delay), byte));
+        if duplicated {
+            result.push((Duration::ZERO, byte));
+        }
+    }
+    result
+}
+
+/// length of the reply packet at the start of `data`, None if there is no complete one
+fn reply_length(data: &[u8]) -> Option<usize> {
+    if data.len() < REPLY_OVERHEAD || data[..2] != REPLY_HEADER {
+        return None;
+    }
+    // special case, the barcode scanner returns a length of 0 when 256 bytes have been returned
+    let length = match data[3] {
+        0 => 256 + REPLY_OVERHEAD,
+        length => length as usize + REPLY_OVERHEAD,
+    };
+    (length <= data.len()).then_some(length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Barcode, Error, OperationMode, Result,
+        interface::BarcodeScanner,
+        retry::{RetryOn, RetryPolicy},
+        simulator::{SimulatedScanner, test_scanner},
+    };
+
+    const HW_VERSION_REPLY: [u8; 7] = [0x02, 0x00, 0x00, 0x01, 0x8C, 0x63, 0x35];
+
+    fn faulty_scanner(
+        retry_policy: RetryPolicy,
+    ) -> Result<BarcodeScanner<FaultyTransport<SimulatedScanner>>> {
+        let mut scanner = test_scanner(FaultyTransport::new(SimulatedScanner::new()), None)?;
+        scanner.set_retry_policy(retry_policy);
+        Ok(scanner)
+    }
+
+    /// scanner in command mode with a barcode waiting to be scanned
+    fn command_mode(
+        protocol_output: bool,
+    ) -> Result<BarcodeScanner<FaultyTransport<SimulatedScanner>>> {
+        let mut scanner = test_scanner(
+            FaultyTransport::new(SimulatedScanner::new()),
+            Some(OperationMode::Command),
+        )?;
+        scanner.set_protocol_output(protocol_output)?;
+        scanner
+            .transport_mut()
+            .inner_mut()
+            .queue_barcode(0x62, b"CODE39")?;
+        Ok(scanner)
+    }
+
+    #[test]
+    fn test_apply_faults() {
+        let data = HW_VERSION_REPLY.to_vec();
+        let bytes = |faults: &[Fault]| -> Vec<u8> {
+            apply_faults(data.clone(), faults)
+                .into_iter()
+                .map(|(_, byte)| byte)
+                .collect()
+        };
+        assert_eq!(bytes(&[]), data);
+        assert_eq!(bytes(&[Fault::CorruptChecksum])[6], 0xCA);
+        assert_eq!(
+            bytes(&[Fault::Corrupt {
+                offset: 1,
+                mask: 0x01
+            }])[..2],
+            [0x02, 0x01]
+        );
+        assert_eq!(
+            bytes(&[Fault::Drop {
+                offset: 4,
+                count: 2
+            }]),
+            [0x02, 0x00, 0x00, 0x01, 0x35]
+        );
+        assert_eq!(
+            bytes(&[Fault::Duplicate { offset: 0 }])[..3],
+            [0x02, 0x02, 0x00]
+        );
+        assert_eq!(bytes(&[Fault::Truncate { offset: 3 }]), [0x02, 0x00, 0x00]);
+        let rejected = bytes(&[Fault::Status(0x01)]);
+        assert_eq!(rejected[2], 0x01);
+        assert!(
+            crate::protocol::ReplyFrame::decode(&rejected)
+                .unwrap()
+                .checksum_ok()
+        );
+        let delayed = apply_faults(
+            data.clone(),
+            &[Fault::Delay {
+                offset: 2,
+                delay: Duration::from_millis(5),
+            }],
+        );
+        assert_eq!(delayed[2].0, Duration::from_millis(5));
+        assert!(delayed[3].0.is_zero());
+    }
+
+    #[test]
+    fn test_checksum_mismatch() -> Result<()> {
+        let mut scanner = faulty_scanner(RetryPolicy::none())?;
+        scanner.transport_mut().schedule(0, Fault::CorruptChecksum);
+        assert!(matches!(
+            scanner.get_hw_version(),
+            Err(Error::ChecksumMismatch { .. })
+        ));
+        assert_eq!(scanner.get_hw_version()?, "V1.40");
+        // the command is repeated after the corrupted reply
+        let mut scanner = faulty_scanner(RetryPolicy::new(3))?;
+        let sent = scanner.transport().commands_sent();
+        scanner.transport_mut().schedule(0, Fault::CorruptChecksum);
+        assert_eq!(scanner.get_hw_version()?, "V1.40");
+        assert_eq!(scanner.transport().commands_sent(), sent + 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bad_header() -> Result<()> {
+        // a reply without a valid header is never recognized
+        let mut scanner = faulty_scanner(RetryPolicy::none())?;
+        scanner.transport_mut().schedule(
+            0,
+            Fault::Corrupt {
+                offset: 1,
+                mask: 0x01,
+            },
+        );
+        assert!(matches!(scanner.get_hw_version(), Err(Error::Timeout)));
+        assert_eq!(scanner.get_hw_version()?, "V1.40");
+        // a scan frame with a corrupted header follows the reply to the scan trigger, it is
+        // dropped while searching for the next header
+        let mut scanner = command_mode(true)?;
+        scanner.set_scan_timeout(Duration::from_millis(300))?;
+        scanner.transport_mut().schedule(
+            0,
+            Fault::Corrupt {
+                offset: 8,
+                mask: 0x01,
+            },
+        );
+        scanner.start_scan()?;
+        assert!(scanner.read_barcode()?.is_none());
+        assert_eq!(scanner.get_hw_version()?, "V1.40");
+        Ok(())
+    }
+
+    #[test]
+    fn test_device_rejected() -> Result<()> {
+        let mut scanner = faulty_scanner(RetryPolicy::new(3))?;
+        scanner.transport_mut().schedule(0, Fault::Status(0x01));
+        // rejected commands are not repeated by default
+        assert!(matches!(
+            scanner.get_hw_version(),
+            Err(Error::DeviceRejected(0x01))
+        ));
+        assert_eq!(scanner.get_hw_version()?, "V1.40");
+        let mut scanner = faulty_scanner(RetryPolicy::new(3).retry_on(&[RetryOn::DeviceRejected]))?;
+        scanner.transport_mut().schedule(0, Fault::Status(0x01));
+        assert_eq!(scanner.get_hw_version()?, "V1.40");
+        Ok(())
+    }
+
+    #[test]
+    fn test_short_read() -> Result<()> {
+        for fault in [
+            Fault::Truncate { offset: 4 },
+            Fault::Drop {
+                offset: 4,
+                count: 1,
+            },
+        ] {
+            let mut scanner = faulty_scanner(RetryPolicy::none())?;
+            scanner.transport_mut().schedule(0, fault.clone());
+            assert!(matches!(scanner.get_hw_version(), Err(Error::Timeout)));
+            assert_eq!(scanner.get_hw_version()?, "V1.40");
+            let mut scanner = faulty_scanner(RetryPolicy::new(3))?;
+            scanner.transport_mut().schedule(0, fault);
+            assert_eq!(scanner.get_hw_version()?, "V1.40");
+        }
+        // a duplicated byte shifts the checksum
+        let mut scanner = faulty_scanner(RetryPolicy::none())?;
+        scanner
+            .transport_mut()
+            .schedule(0, Fault::Duplicate { offset: 4 });
+        assert!(matches!(
+            scanner.get_hw_version(),
+            Err(Error::ChecksumMismatch { .. })
+        ));
+        assert_eq!(scanner.get_hw_version()?, "V1.40");
+        Ok(())
+    }
+
+    #[test]
+    fn test_delay() -> Result<()> {
+        // a delay within the read timeout is not noticed
+        let mut scanner = faulty_scanner(RetryPolicy::none())?;
+        scanner.transport_mut().schedule(
+            0,
+            Fault::Delay {
+                offset: 3,
+                delay: Duration::from_millis(5),
+            },
+        );
+        assert_eq!(scanner.get_hw_version()?, "V1.40");
+        // a late reply times out, the command is repeated
+        let mut scanner = faulty_scanner(RetryPolicy::none())?;
+        scanner.transport_mut().schedule(
+            0,
+            Fault::Delay {
+                offset: 3,
+                delay: Duration::from_millis(50),
+            },
+        );
+        assert!(matches!(scanner.get_hw_version(), Err(Error::Timeout)));
+        let mut scanner = faulty_scanner(RetryPolicy::new(3))?;
+        scanner.transport_mut().schedule(
+            0,
+            Fault::Delay {
+                offset: 3,
+                delay: Duration::from_millis(50),
+            },
+        );
+        assert_eq!(scanner.get_hw_version()?, "V1.40");
+        assert!(!scanner.transport().faults_pending());
+        Ok(())
+    }
+
+    #[test]
+    fn test_partial_barcode() -> Result<()> {
+        for protocol_output in [false, true] {
+            let mut scanner = command_mode(protocol_output)?;
+            // the barcode follows the 7 byte reply to the scan trigger
+            scanner
+                .transport_mut()
+                .schedule(0, Fault::Truncate { offset: 10 });
+            scanner.start_scan()?;
+            assert!(matches!(scanner.read_barcode(), Err(Error::Timeout)));
+            assert_eq!(scanner.get_hw_version()?, "V1.40");
+            // the next scan is received completely
+            scanner
+                .transport_mut()
+                .inner_mut()
+                .queue_barcode(0x62, b"CODE39")?;
+            scanner.start_scan()?;
+            assert!(matches!(
+                scanner.read_barcode()?.map(|scan| scan.barcode),
+                Some(Barcode::Code39(data)) if data == "CODE39"
+            ));
+        }
+        Ok(())
+    }
+}
@@ -1,23 +1,55 @@
-use std::time::{Duration, Instant};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
 
-use anyhow::{Result, anyhow};
 use chrono::NaiveDate;
-use serial2::{CharSize, FlowControl, Parity, SerialPort, Settings, StopBits};
+use serial2::SerialPort;
 use tracing::{debug, trace};
 
 use crate::{
-    Barcode, Barcodes, IlluminationMode, OperationMode, ScanArea, TargetLightMode,
-    crc::{calculate_crc, verify_crc},
+    Barcodes, Error, IlluminationMode, OperationMode, Result, ScanArea, ScannerMode, Symbology,
+    SymbologyStatus, TargetLightMode,
+    builder::{BarcodeScannerBuilder, OutputFormat},
+    protocol::{
+        BARCODE_RESULT_OPTIONS, DecoderEvent, END_OF_DATA_GAP, FUNCTION_SAVE, FUNCTION_WRITE,
+        PROTOCOL_RESULT_OPTIONS, REPLY_OVERHEAD, ReplyDecoder, ReplyFrame, SCAN_FRAME_HEADER,
+        SCAN_FRAME_OVERHEAD, barcode_type_writes, decode_baud_rate, decode_date, decode_reply,
+        decode_scan_frame, decode_scan_timeout, decode_symbology_status, decode_version,
+        encode_baud_rate, encode_command, encode_raw_command, encode_read_command,
+        encode_scan_timeout, encode_write_command, is_known_code_id, output_options_supported,
+        reply_deadline, scan_frame_data_length,
+    },
+    registers::{
+        BAUD_RATE, FACTORY_RESET, FACTORY_RESET_VALUE, HW_VERSION, RESULT_OPTIONS, Register,
+        SCAN_TIMEOUT, SW_DATE, SW_VERSION, ScanAreaAndBarcodes, ScanTrigger, SettingCode,
+        SymbologyEnable,
+    },
+    retry::RetryPolicy,
+    scan::{CancelHandle, ScanEvent},
+    transport::Transport,
 };
 
-pub struct BarcodeScanner {
-    /// serial or USB port to communicate over
-    port: SerialPort,
+pub struct BarcodeScanner<T: Transport = SerialPort> {
+    /// serial or USB port (or any other transport) to communicate over
+    port: T,
     /// timeout for a single scan (in manual or command mode), default is 5s
     scan_timeout: Duration,
+    /// barcode data received while waiting for a command reply (continuous or sensing mode)
+    unsolicited_data: VecDeque<u8>,
+    /// barcodes are sent in scan frames with a checksum instead of plain text
+    protocol_output: bool,
+    /// policy for repeating failed command exchanges
+    retry_policy: RetryPolicy,
+    /// the payload of barcodes starts with an AIM symbology identifier
+    aim_id_prefix: bool,
+    /// time of the last scan trigger which did not return a barcode yet
+    triggered_at: Option<Instant>,
+    /// cancels waiting for a barcode, shared with other threads
+    cancel: CancelHandle,
 }
 
-impl BarcodeScanner {
+impl BarcodeScanner<SerialPort> {
     /// open the serial port and initialize the necessary device options for scanning
     ///
     /// # Arguments
@@ -29,7 +61,7 @@ impl BarcodeScanner {
     /// ```no_run
     /// use anyhow::Result;
     /// use waveshare_barcodescanner::interface::BarcodeScanner;
-    /// 
+    ///
     /// fn main() -> Result<()> {
     ///     let scanner = BarcodeScanner::new("/dev/serial0")?;
     ///     Ok(())
@@ -39,37 +71,200 @@ impl BarcodeScanner {
     /// # Notes
     ///
     /// The device is expected to be in UART mode, with the serial options set to 9600,8,N,1 (factory default).
+    /// Use [`BarcodeScanner::builder`] for other serial options or to open the device without changing its settings.
     pub fn new(serial_port_name: &str) -> Result<Self> {
-        let mut port = SerialPort::open(serial_port_name, |mut settings: Settings| {
-            settings.set_raw();
-            settings.set_baud_rate(9600)?;
-            settings.set_char_size(CharSize::Bits8);
-            settings.set_stop_bits(StopBits::One);
-            settings.set_parity(Parity::None);
-            settings.set_flow_control(FlowControl::None);
-            Ok(settings)
-        })?;
+        BarcodeScannerBuilder::new().open(serial_port_name)
+    }
+
+    /// open the serial port at whichever common baud rate the barcode scanner answers on
+    ///
+    /// # Arguments
+    ///
+    /// * `serial_port_name` the device name of the serial port to open
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use waveshare_barcodescanner::interface::BarcodeScanner;
+    ///
+    /// fn main() -> Result<()> {
+    ///     let mut scanner = BarcodeScanner::detect("/dev/serial0")?;
+    ///     scanner.set_baud_rate(9600)?;
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Notes
+    ///
+    /// see [`BarcodeScannerBuilder::detect`]
+    pub fn detect(serial_port_name: &str) -> Result<Self> {
+        BarcodeScannerBuilder::new().detect(serial_port_name)
+    }
+
+    /// return a builder to open the barcode scanner with custom options
+    ///
+    /// see [`BarcodeScannerBuilder`]
+    pub fn builder() -> BarcodeScannerBuilder {
+        BarcodeScannerBuilder::new()
+    }
+}
+
+impl<T: Transport> BarcodeScanner<T> {
+    /// use an already opened transport and initialize the necessary device options for scanning
+    ///
+    /// # Arguments
+    ///
+    /// * `transport` the connection to the barcode scanner, configured with a short read timeout (e.g. 100ms)
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use anyhow::Result;
+    /// use serial2::SerialPort;
+    /// use waveshare_barcodescanner::interface::BarcodeScanner;
+    ///
+    /// fn main() -> Result<()> {
+    ///     let mut port = SerialPort::open("/dev/serial0", 9600)?;
+    ///     port.set_read_timeout(Duration::from_millis(100))?;
+    ///     let scanner = BarcodeScanner::with_transport(port)?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_transport(port: T) -> Result<Self> {
+        BarcodeScanner::initialize(
+            port,
+            Duration::from_secs(5),
+            OutputFormat::Text,
+            false,
+            RetryPolicy::none(),
+        )
+    }
+
+    /// initialize the barcode scanner on an opened transport
+    ///
+    /// # Arguments
+    ///
+    /// * `port` the connection to the barcode scanner
+    /// * `scan_timeout` the time to wait for a barcode in [`BarcodeScanner::read_barcode`]
+    /// * `output_format` the barcode result options to write to the device, if any
+    /// * `verify` if true the barcode result options are read back and checked
+    /// * `retry_policy` the policy for repeating failed command exchanges
+    pub(crate) fn initialize(
+        mut port: T,
+        scan_timeout: Duration,
+        output_format: OutputFormat,
+        verify: bool,
+        retry_policy: RetryPolicy,
+    ) -> Result<Self> {
         port.discard_buffers()?;
-        port.set_read_timeout(Duration::from_millis(100))?;
         let mut scanner = BarcodeScanner {
             port,
-            scan_timeout: Duration::from_secs(5),
+            scan_timeout,
+            unsolicited_data: VecDeque::new(),
+            protocol_output: output_format == OutputFormat::Protocol,
+            retry_policy,
+            aim_id_prefix: false,
+            triggered_at: None,
+            cancel: CancelHandle::new(),
+        };
+        // in order for barcode payload decoding to work corectly, set the decoding options
+        let options = match output_format {
+            OutputFormat::Text => Some(BARCODE_RESULT_OPTIONS),
+            OutputFormat::Protocol => Some(PROTOCOL_RESULT_OPTIONS),
+            OutputFormat::Unchanged => None,
         };
-        // in order for barcode payload decoding to work corectly, always set the decoding options
-        let mut default_barcode_result_options: u8 = 0x00;
-        // without protocol
-        // CR as end of line
-        // without RF
-        // without prefix
-        // with CodeID
-        default_barcode_result_options |= 0x04;
-        // without suffix
-        // with end character
-        default_barcode_result_options |= 0x01;
-        scanner.send_write_command(0x0060, &[default_barcode_result_options])?;
+        if let Some(options) = options {
+            scanner.send_write_command(RESULT_OPTIONS, &[options])?;
+        }
+        if verify {
+            let mut buffer: [u8; 1] = [0x00; 1];
+            scanner.send_read_command_fixed_reply(RESULT_OPTIONS, &mut buffer)?;
+            match options {
+                Some(expected) if buffer[0] != expected => {
+                    return Err(Error::ConfigurationMismatch {
+                        address: RESULT_OPTIONS,
+                        expected,
+                        actual: buffer[0],
+                    });
+                }
+                Some(_) => {}
+                None => {
+                    // the options have been left as they are, use whatever is configured
+                    if !output_options_supported(buffer[0]) {
+                        return Err(Error::InvalidData(format!(
+                            "barcode result options {:02X} are not supported",
+                            buffer[0]
+                        )));
+                    }
+                    scanner.protocol_output = buffer[0] & 0x80 != 0;
+                }
+            }
+        }
         Ok(scanner)
     }
 
+    /// return a reference to the underlying transport
+    pub fn transport(&self) -> &T {
+        &self.port
+    }
+
+    /// return a mutable reference to the underlying transport
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.port
+    }
+
+    /// consume the scanner and return the underlying transport
+    pub fn into_transport(self) -> T {
+        self.port
+    }
+
+    /// set the policy for repeating failed command exchanges
+    ///
+    /// see [`RetryPolicy`]
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    /// return the policy for repeating failed command exchanges
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    /// change the speed of the serial connection
+    ///
+    /// The new speed is written to the barcode scanner, afterwards the transport is switched
+    /// over and the connection is confirmed by reading the hardware version.
+    ///
+    /// # Arguments
+    ///
+    /// * `baud_rate` one of 1200, 2400, 4800, 9600, 14400, 19200, 38400, 57600 or 115200
+    ///
+    /// # Note
+    ///
+    /// Call [`BarcodeScanner::save_to_flash`] to keep the new speed after a power cycle. If
+    /// the confirmation fails, use [`BarcodeScanner::detect`] to find the device again.
+    pub fn set_baud_rate(&mut self, baud_rate: u32) -> Result<()> {
+        let value = encode_baud_rate(baud_rate)?;
+        // the reply is still sent with the previous speed
+        self.send_write_command(BAUD_RATE, &value)?;
+        debug!("switching to {} baud", baud_rate);
+        self.port.set_baud_rate(baud_rate)?;
+        self.port.discard_buffers()?;
+        self.unsolicited_data.clear();
+        self.get_hw_version()?;
+        Ok(())
+    }
+
+    /// return the speed the barcode scanner is configured for
+    pub fn get_baud_rate(&mut self) -> Result<u32> {
+        let mut buffer: [u8; 2] = [0x00; 2];
+        self.read_registers_into(BAUD_RATE, &mut buffer)?;
+        decode_baud_rate(buffer)
+    }
+
     /// return the hardware version of the attached barcode scanner
     ///
     /// # Examples
@@ -77,7 +272,7 @@ impl BarcodeScanner {
     /// ```no_run
     /// use anyhow::Result;
     /// use waveshare_barcodescanner::interface::BarcodeScanner;
-    /// 
+    ///
     /// fn main() -> Result<()> {
     ///     let mut scanner = BarcodeScanner::new("/dev/serial0")?;
     ///     println!("Detected barcode scanner hardware version: {}", scanner.get_hw_version()?);
@@ -86,21 +281,14 @@ impl BarcodeScanner {
     /// ```
     pub fn get_hw_version(&mut self) -> Result<String> {
         let mut buffer: [u8; 1] = [0; 1];
-        let bytes_read = self.send_read_command_fixed_reply(0x00E1, &mut buffer)?;
+        let bytes_read = self.send_read_command_fixed_reply(HW_VERSION, &mut buffer)?;
         if bytes_read != 1 {
-            return Err(anyhow!(
-                "incorrect number of bytes read from device, expected 1 received {}",
-                bytes_read
-            ));
-        }
-        match buffer[0] {
-            0x64 => Ok(String::from("V1.00")),
-            0x6E => Ok(String::from("V1.10")),
-            0x78 => Ok(String::from("V1.20")),
-            0x82 => Ok(String::from("V1.30")),
-            0x8C => Ok(String::from("V1.40")),
-            _ => Ok(format!("unknown {:0X}", buffer[0])),
+            return Err(Error::UnexpectedLength {
+                expected: 1,
+                received: bytes_read,
+            });
         }
+        Ok(decode_version(buffer[0]))
     }
 
     /// return the software version of the attached barcode scanner
@@ -110,7 +298,7 @@ impl BarcodeScanner {
     /// ```no_run
     /// use anyhow::Result;
     /// use waveshare_barcodescanner::interface::BarcodeScanner;
-    /// 
+    ///
     /// fn main() -> Result<()> {
     ///     let mut scanner = BarcodeScanner::new("/dev/serial0")?;
     ///     println!("Detected barcode scanner software version: {}", scanner.get_sw_version()?);
@@ -119,21 +307,14 @@ impl BarcodeScanner {
     /// ```
     pub fn get_sw_version(&mut self) -> Result<String> {
         let mut buffer: [u8; 1] = [0; 1];
-        let bytes_read = self.send_read_command_fixed_reply(0x00E2, &mut buffer)?;
+        let bytes_read = self.send_read_command_fixed_reply(SW_VERSION, &mut buffer)?;
         if bytes_read != 1 {
-            return Err(anyhow!(
-                "incorrect number of bytes read from device, expected 1 received {}",
-                bytes_read
-            ));
-        }
-        match buffer[0] {
-            0x64 => Ok(String::from("V1.00")),
-            0x6E => Ok(String::from("V1.10")),
-            0x78 => Ok(String::from("V1.20")),
-            0x82 => Ok(String::from("V1.30")),
-            0x8C => Ok(String::from("V1.40")),
-            _ => Ok(format!("unknown {:0X}", buffer[0])),
+            return Err(Error::UnexpectedLength {
+                expected: 1,
+                received: bytes_read,
+            });
         }
+        Ok(decode_version(buffer[0]))
     }
 
     /// return the software date of the attached barcode scanner
@@ -143,7 +324,7 @@ impl BarcodeScanner {
     /// ```no_run
     /// use anyhow::Result;
     /// use waveshare_barcodescanner::interface::BarcodeScanner;
-    /// 
+    ///
     /// fn main() -> Result<()> {
     ///     let mut scanner = BarcodeScanner::new("/dev/serial0")?;
     ///     println!("Detected barcode scanner software build date: {}", scanner.get_sw_date()?.format("%Y/%m/%d"));
@@ -152,43 +333,31 @@ impl BarcodeScanner {
     /// ```
     pub fn get_sw_date(&mut self) -> Result<NaiveDate> {
         let mut buffer: [u8; 1] = [0; 1];
-        let bytes_read = self.send_read_command_fixed_reply(0x00E3, &mut buffer)?;
+        let bytes_read = self.send_read_command_fixed_reply(SW_DATE, &mut buffer)?;
         if bytes_read != 1 {
-            return Err(anyhow!(
-                "incorrect number of bytes read from device, expected 1 received {}",
-                bytes_read
-            ));
-        }
-        // only the years past year 2000 are returned
-        let year: i32 = buffer[0] as i32 + 2000;
-        let bytes_read = self.send_read_command_fixed_reply(0x00E4, &mut buffer)?;
+            return Err(Error::UnexpectedLength {
+                expected: 1,
+                received: bytes_read,
+            });
+        }
+        let year = buffer[0];
+        let bytes_read = self.send_read_command_fixed_reply(SW_DATE + 1, &mut buffer)?;
         if bytes_read != 1 {
-            return Err(anyhow!(
-                "incorrect number of bytes read from device, expected 1 received {}",
-                bytes_read
-            ));
+            return Err(Error::UnexpectedLength {
+                expected: 1,
+                received: bytes_read,
+            });
         }
-        let month: u32 = buffer[0] as u32;
-        let bytes_read = self.send_read_command_fixed_reply(0x00E5, &mut buffer)?;
+        let month = buffer[0];
+        let bytes_read = self.send_read_command_fixed_reply(SW_DATE + 2, &mut buffer)?;
         if bytes_read != 1 {
-            return Err(anyhow!(
-                "incorrect number of bytes read from device, expected 1 received {}",
-                bytes_read
-            ));
-        }
-        let day: u32 = buffer[0] as u32;
-        let date = match NaiveDate::from_ymd_opt(year, month, day) {
-            Some(date) => date,
-            None => {
-                return Err(anyhow!(
-                    "unable to construct date from  year {} month {} day {}",
-                    year,
-                    month,
-                    day
-                ));
-            }
-        };
-        Ok(date)
+            return Err(Error::UnexpectedLength {
+                expected: 1,
+                received: bytes_read,
+            });
+        }
+        let day = buffer[0];
+        decode_date(year, month, day)
     }
 
     /// start scanning for barcodes
@@ -198,12 +367,12 @@ impl BarcodeScanner {
     /// ```no_run
     /// use anyhow::Result;
     /// use waveshare_barcodescanner::interface::BarcodeScanner;
-    /// 
+    ///
     /// fn main() -> Result<()> {
     ///     let mut scanner = BarcodeScanner::new("/dev/serial0")?;
     ///     println!("Please scan your badge for identification.");
     ///     scanner.start_scan()?;
-    ///     match scanner.read_barcode()? {
+    ///     match scanner.read_barcode()?.map(|scan| scan.barcode) {
     ///         Some(barcode) => println!("data scanned: {}", barcode),
     ///         None => println!("Please try again. Make sure the barcode on your badge is at the center of the green scanning light."),
     ///     }
@@ -211,7 +380,11 @@ impl BarcodeScanner {
     /// }
     /// ```
     pub fn start_scan(&mut self) -> Result<()> {
-        self.send_write_command(0x0002, &[0x01])
+        // a cancellation left over from an earlier scan does not apply to this one
+        self.cancel.clear();
+        self.write_register(&ScanTrigger { scanning: true })?;
+        self.triggered_at = Some(Instant::now());
+        Ok(())
     }
 
     /// stop scanning for barcodes
@@ -220,91 +393,168 @@ impl BarcodeScanner {
     ///
     /// When a barcode has been scanned, the scanner automatically stops scanning in manual and command mode.
     pub fn stop_scan(&mut self) -> Result<()> {
-        self.send_write_command(0x0002, &[0x00])
+        self.triggered_at = None;
+        self.write_register(&ScanTrigger { scanning: false })
+    }
+
+    /// return the time [`BarcodeScanner::read_barcode`] waits for a barcode
+    pub(crate) fn host_scan_timeout(&self) -> Duration {
+        self.scan_timeout
     }
 
     /// waits for a barcode payload to be returned from the scanner
     ///
     /// will return immediately when the payload has been read or the `scan_timeout` has been reached
-    pub fn read_barcode(&mut self) -> Result<Option<Barcode>> {
+    ///
+    /// # Notes
+    ///
+    /// In plain text output mode the end of a payload is detected by a CR which is not followed
+    /// by more data within [`END_OF_DATA_GAP`]. A payload containing a CR followed by a pause is
+    /// cut off there, and two barcodes sent back to back may be returned as one. Binary payloads
+    /// should be read in protocol output mode ([`OutputFormat::Protocol`]), which is length
+    /// delimited.
+    ///
+    /// # Returns
+    ///
+    /// * the barcode together with the details of the scan, None if no barcode was scanned
+    /// * [`Error::Cancelled`] if the read was cancelled with the [`CancelHandle`], scanning is
+    ///   stopped in this case
+    pub fn read_barcode(&mut self) -> Result<Option<ScanEvent>> {
+        let result = self.read_barcode_until_cancelled();
+        // a cancellation arriving after the read returned does not apply to the next read
+        self.cancel.clear();
+        result
+    }
+
+    /// waits for a barcode like [`BarcodeScanner::read_barcode`], a pending cancellation is consumed
+    fn read_barcode_until_cancelled(&mut self) -> Result<Option<ScanEvent>> {
+        let deadline = Instant::now() + self.scan_timeout;
+        // wait a single read timeout at a time, so a cancellation is noticed
+        let poll_timeout = self
+            .port
+            .read_timeout()
+            .unwrap_or(Duration::from_millis(100));
+        loop {
+            if self.cancel.take() {
+                debug!("waiting for barcode data cancelled");
+                if let Err(err) = self.stop_scan() {
+                    debug!("stopping the scan after the cancellation failed: {}", err);
+                }
+                return Err(Error::Cancelled);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                debug!("timeout waiting for barcode data");
+                return Ok(None);
+            }
+            if let Some(scan) = self.read_barcode_within(remaining.min(poll_timeout))? {
+                return Ok(Some(scan));
+            }
+        }
+    }
+
+    /// return a handle to cancel [`BarcodeScanner::read_barcode`] from another thread
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use waveshare_barcodescanner::{Error, interface::BarcodeScanner};
+    ///
+    /// fn main() -> Result<()> {
+    ///     let mut scanner = BarcodeScanner::new("/dev/serial0")?;
+    ///     let cancel = scanner.cancel_handle();
+    ///     std::thread::spawn(move || {
+    ///         // e.g. the user closed the window while waiting for a barcode
+    ///         std::thread::sleep(std::time::Duration::from_secs(1));
+    ///         cancel.cancel();
+    ///     });
+    ///     scanner.start_scan()?;
+    ///     match scanner.read_barcode() {
+    ///         Ok(Some(scan)) => println!("data scanned: {}", scan.barcode),
+    ///         Ok(None) => println!("no barcode scanned"),
+    ///         Err(Error::Cancelled) => println!("scan cancelled"),
+    ///         Err(err) => return Err(err.into()),
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    /// waits for a barcode payload to be returned from the scanner for at most `timeout`
+    pub(crate) fn read_barcode_within(&mut self, timeout: Duration) -> Result<Option<ScanEvent>> {
+        if self.protocol_output {
+            return self.read_scan_frame_within(timeout);
+        }
         // read the code ID
         let mut codeid_buffer: [u8; 1] = [0x00; 1];
-        self.read_from_serial_exact(&mut codeid_buffer, Some(self.scan_timeout))?;
+        self.read_from_serial_exact(&mut codeid_buffer, Some(timeout))?;
         if codeid_buffer[0] == 0x00 {
             // no data received until the timout was reached
             // 0x00 is an invalid Code ID
             debug!("timeout waiting for barcode data");
             return Ok(None);
         }
-        let mut lines: Vec<Vec<u8>> = Vec::new();
-        loop {
-            trace!("reading next line");
-            let (read_line, end_of_data) = self.read_line_from_serial()?;
-            if read_line.len() > 0 {
-                lines.push(read_line);
-            }
-            if end_of_data {
-                // no more data to read
-                trace!("no more data to read");
-                break;
-            }
-        }
-        if lines.is_empty() {
-            debug!("no barcode data was read from the device");
-            return Ok(None);
-        }
-        debug!(
-            "{} line(s) of barcode data was read from the device",
-            lines.len()
-        );
-        match codeid_buffer[0] {
-            0x65 => {
-                debug!("Interleaved 2of5");
-                Ok(Some(Barcode::Interleaved2of5(
-                    lines.get(0).unwrap().iter().map(|&c| c as char).collect(),
-                )))
-            }
-            0x62 => {
-                debug!("Code39");
-                Ok(Some(Barcode::Code39(
-                    lines.get(0).unwrap().iter().map(|&c| c as char).collect(),
-                )))
-            }
-            0x64 => {
-                debug!("EAN13");
-                Ok(Some(Barcode::EAN13(
-                    lines.get(0).unwrap().iter().map(|&c| c as char).collect(),
-                )))
-            }
-            0x6A => {
-                debug!("Code128");
-                Ok(Some(Barcode::Code128(
-                    lines.get(0).unwrap().iter().map(|&c| c as char).collect(),
-                )))
-            }
-            0x51 => {
-                debug!("QR code");
-                Ok(Some(Barcode::QR(
-                    lines
-                        .iter()
-                        .map(|line| line.iter().map(|&c| c as char).collect())
-                        .collect(),
-                )))
-            }
-            0x75 => {
-                debug!("Dot Matrix code");
-                Ok(Some(Barcode::DotMatrix(
-                    lines
-                        .iter()
-                        .map(|line| line.iter().map(|&c| c as char).collect())
-                        .collect(),
-                )))
-            }
-            _ => Err(anyhow!(
-                "unsupported barcode type received: {:02X}",
-                codeid_buffer[0]
-            )),
-        }
+        let payload = self.read_payload_from_serial()?;
+        self.scan_event(codeid_buffer[0], payload)
+    }
+
+    /// the payload of barcodes starts with an AIM symbology identifier
+    ///
+    /// The identifier is added by the barcode scanner when configured with the respective setup
+    /// code. If enabled, it is split off the payload and returned in [`ScanEvent::aim_id`].
+    pub fn set_aim_id_prefix(&mut self, enable: bool) {
+        self.aim_id_prefix = enable;
+    }
+
+    /// return true if the payload of barcodes is expected to start with an AIM symbology identifier
+    pub fn aim_id_prefix(&self) -> bool {
+        self.aim_id_prefix
+    }
+
+    /// enable/disable the protocol output mode of the barcode scanner
+    ///
+    /// In protocol output mode the barcode scanner sends each barcode in a frame with a header,
+    /// a length and a checksum instead of plain text terminated by CR. Corrupted scans are
+    /// reported as [`Error::ChecksumMismatch`], which is recommended on noisy connections
+    /// (e.g. long RS-232 cables).
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` if true barcodes are sent in scan frames, otherwise as plain text
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use waveshare_barcodescanner::interface::BarcodeScanner;
+    ///
+    /// fn main() -> Result<()> {
+    ///     let mut scanner = BarcodeScanner::new("/dev/serial0")?;
+    ///     scanner.set_protocol_output(true)?;
+    ///     scanner.start_scan()?;
+    ///     if let Some(barcode) = scanner.read_barcode()?.map(|scan| scan.barcode) {
+    ///         println!("data scanned: {}", barcode);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn set_protocol_output(&mut self, enable: bool) -> Result<()> {
+        let options = if enable {
+            PROTOCOL_RESULT_OPTIONS
+        } else {
+            BARCODE_RESULT_OPTIONS
+        };
+        self.send_write_command(RESULT_OPTIONS, &[options])?;
+        self.protocol_output = enable;
+        Ok(())
+    }
+
+    /// return true if barcodes are expected in scan frames (protocol output mode)
+    pub fn protocol_output(&self) -> bool {
+        self.protocol_output
     }
 
     /// set the mode of operation and light/buzzer parameters
@@ -319,7 +569,8 @@ impl BarcodeScanner {
     ///
     /// # Note
     ///
-    /// Only manual and command scanning operations are currently supported.
+    /// In continuous and sensing mode the scanner sends barcodes on its own. Use
+    /// [`BarcodeScanner::into_background`] to receive them while still being able to change settings.
     pub fn set_mode(
         &mut self,
         enable_led_indication_on_successful_scan: bool,
@@ -328,30 +579,34 @@ impl BarcodeScanner {
         illumination_mode: IlluminationMode,
         operation_mode: OperationMode,
     ) -> Result<()> {
-        let mut operation: u8 = 0x0;
-        if enable_led_indication_on_successful_scan {
-            operation |= 0x80;
-        }
-        if enable_buzzer {
-            operation |= 0x40;
-        }
-        match target_light_mode {
-            TargetLightMode::AlwaysOff => {}
-            TargetLightMode::AlwaysOn => operation |= 0x20,
-            TargetLightMode::Standard => operation |= 0x10,
-        }
-        match illumination_mode {
-            IlluminationMode::AlwaysOff => {}
-            IlluminationMode::AlwaysOn => operation |= 0x08,
-            IlluminationMode::Standard => operation |= 0x04,
-        }
-        match operation_mode {
-            OperationMode::Manual => {}
-            OperationMode::Command => operation |= 0x01,
-            OperationMode::Continuous => operation |= 0x02,
-            OperationMode::Sensing => operation |= 0x03,
-        }
-        self.send_write_command(0x0000, &[operation])
+        self.write_register(&ScannerMode {
+            led_indication: enable_led_indication_on_successful_scan,
+            buzzer: enable_buzzer,
+            target_light_mode,
+            illumination_mode,
+            operation_mode,
+        })
+    }
+
+    /// return the mode of operation and light/buzzer parameters
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use waveshare_barcodescanner::{OperationMode, interface::BarcodeScanner};
+    ///
+    /// fn main() -> Result<()> {
+    ///     let mut scanner = BarcodeScanner::new("/dev/serial0")?;
+    ///     let mode = scanner.get_mode()?;
+    ///     if mode.operation_mode != OperationMode::Command {
+    ///         println!("unexpected operation mode: {:?}", mode.operation_mode);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn get_mode(&mut self) -> Result<ScannerMode> {
+        self.read_register()
     }
 
     /// set the scanning area and barcodes allowed
@@ -365,17 +620,21 @@ impl BarcodeScanner {
         scan_area: ScanArea,
         allowed_barcodes: Barcodes,
     ) -> Result<()> {
-        let mut scanner_setting: u8 = 0x0;
-        match scan_area {
-            ScanArea::All => {}
-            ScanArea::Center => scanner_setting |= 0x08,
-        }
-        match allowed_barcodes {
-            Barcodes::EnableAll => scanner_setting |= 0x02,
-            Barcodes::DisableAll => {}
-            Barcodes::Default => scanner_setting |= 0x04,
-        }
-        self.send_write_command(0x002C, &[scanner_setting])
+        self.write_register(&ScanAreaAndBarcodes {
+            scan_area,
+            barcodes: allowed_barcodes,
+        })
+    }
+
+    /// return the scanning area and barcodes allowed
+    pub fn get_scan_area_and_barcodes(&mut self) -> Result<(ScanArea, Barcodes)> {
+        let register: ScanAreaAndBarcodes = self.read_register()?;
+        Ok((register.scan_area, register.barcodes))
+    }
+
+    /// return the scanning area
+    pub fn get_scan_area(&mut self) -> Result<ScanArea> {
+        Ok(self.get_scan_area_and_barcodes()?.0)
     }
 
     /// set the maximum time for a manual or command scan before the scanner goes inactive again
@@ -388,63 +647,70 @@ impl BarcodeScanner {
     ///
     /// By default the barcode scanner is set to 5s of scanning duration
     pub fn set_scan_timeout(&mut self, scan_timeout: Duration) -> Result<()> {
-        if scan_timeout > Duration::from_millis(25500) {
-            return Err(anyhow!(
-                "timeout is too big, maximum value is 25500 ms (25.5s)"
-            ));
-        }
-        if scan_timeout == Duration::from_millis(0) {
-            return Err(anyhow!("this crate does not support indefinite waiting"));
-        }
-        let scan_timeout_byte: u8 = (scan_timeout.as_millis() / 100).try_into()?;
-        self.send_write_command(0x0006, &[scan_timeout_byte])?;
+        let scan_timeout_byte = encode_scan_timeout(scan_timeout)?;
+        self.send_write_command(SCAN_TIMEOUT, &[scan_timeout_byte])?;
         self.scan_timeout = scan_timeout;
         Ok(())
     }
 
+    /// let the barcode scanner scan without a timeout in manual or command mode
+    ///
+    /// # Note
+    ///
+    /// [`BarcodeScanner::read_barcode`] keeps waiting for the last scan timeout set
+    pub fn disable_scan_timeout(&mut self) -> Result<()> {
+        self.send_write_command(SCAN_TIMEOUT, &[0x00])
+    }
+
+    /// return the scan timeout the barcode scanner is configured for
+    ///
+    /// # Note
+    ///
+    /// A zero duration means the barcode scanner scans without a timeout.
+    pub fn get_scan_timeout(&mut self) -> Result<Duration> {
+        let mut buffer: [u8; 1] = [0x00; 1];
+        self.read_registers_into(SCAN_TIMEOUT, &mut buffer)?;
+        Ok(decode_scan_timeout(buffer[0]))
+    }
+
     /// enable/disable barcode type: EAN13
     pub fn allow_ean13(&mut self, enable: bool) -> Result<()> {
-        if enable {
-            self.send_write_command(0x002E, &[0x01])
-        } else {
-            self.send_write_command(0x002E, &[0x00])
-        }
+        self.allow_barcode_type(Symbology::EAN13.enable_address(), enable.into(), None, None)
     }
 
     /// enable/disable barcode type: EAN8
     pub fn allow_ean8(&mut self, enable: bool) -> Result<()> {
-        if enable {
-            self.send_write_command(0x002F, &[0x01])
-        } else {
-            self.send_write_command(0x002F, &[0x00])
-        }
+        self.allow_barcode_type(Symbology::EAN8.enable_address(), enable.into(), None, None)
     }
 
     /// enable/disable barcode type: GS1 Databar Stacked(RSS)
     pub fn allow_rss_stack(&mut self, enable: bool) -> Result<()> {
-        if enable {
-            self.send_write_command(0x0026, &[0x01])
-        } else {
-            self.send_write_command(0x0026, &[0x00])
-        }
+        self.allow_barcode_type(
+            Symbology::GS1DatabarStacked.enable_address(),
+            enable.into(),
+            None,
+            None,
+        )
     }
 
     /// enable/disable barcode type: GS1 Databar(RSS-14)
     pub fn allow_rss14(&mut self, enable: bool) -> Result<()> {
-        if enable {
-            self.send_write_command(0x004F, &[0x01])
-        } else {
-            self.send_write_command(0x004F, &[0x00])
-        }
+        self.allow_barcode_type(
+            Symbology::GS1Databar.enable_address(),
+            enable.into(),
+            None,
+            None,
+        )
     }
 
     /// enable/disable barcode type: GS1 Databar Limited(RSS)
     pub fn allow_limited_rss(&mut self, enable: bool) -> Result<()> {
-        if enable {
-            self.send_write_command(0x0050, &[0x01])
-        } else {
-            self.send_write_command(0x0050, &[0x00])
-        }
+        self.allow_barcode_type(
+            Symbology::GS1DatabarLimited.enable_address(),
+            enable.into(),
+            None,
+            None,
+        )
     }
 
     /// enable/disable barcode type: GS1 Databar Expanded(RSS)
@@ -457,17 +723,12 @@ impl BarcodeScanner {
         min_length: Option<u8>,
         max_length: Option<u8>,
     ) -> Result<()> {
-        if enable {
-            if let Some(min_length) = min_length {
-                self.send_write_command(0x0052, &[min_length])?;
-            }
-            if let Some(max_length) = max_length {
-                self.send_write_command(0x0053, &[max_length])?;
-            }
-            self.send_write_command(0x0051, &[0x01])
-        } else {
-            self.send_write_command(0x0051, &[0x00])
-        }
+        self.allow_barcode_type(
+            Symbology::GS1DatabarExpanded.enable_address(),
+            enable.into(),
+            min_length,
+            max_length,
+        )
     }
 
     /// enable/disable barcode type: MSI-Plessey
@@ -480,17 +741,12 @@ impl BarcodeScanner {
         min_length: Option<u8>,
         max_length: Option<u8>,
     ) -> Result<()> {
-        if enable {
-            if let Some(min_length) = min_length {
-                self.send_write_command(0x004D, &[min_length])?;
-            }
-            if let Some(max_length) = max_length {
-                self.send_write_command(0x004E, &[max_length])?;
-            }
-            self.send_write_command(0x004C, &[0x01])
-        } else {
-            self.send_write_command(0x004C, &[0x00])
-        }
+        self.allow_barcode_type(
+            Symbology::MSI.enable_address(),
+            enable.into(),
+            min_length,
+            max_length,
+        )
     }
 
     /// enable/disable barcode type: Code11
@@ -503,17 +759,12 @@ impl BarcodeScanner {
         min_length: Option<u8>,
         max_length: Option<u8>,
     ) -> Result<()> {
-        if enable {
-            if let Some(min_length) = min_length {
-                self.send_write_command(0x004A, &[min_length])?;
-            }
-            if let Some(max_length) = max_length {
-                self.send_write_command(0x004B, &[max_length])?;
-            }
-            self.send_write_command(0x0049, &[0x01])
-        } else {
-            self.send_write_command(0x0049, &[0x00])
-        }
+        self.allow_barcode_type(
+            Symbology::Code11.enable_address(),
+            enable.into(),
+            min_length,
+            max_length,
+        )
     }
 
     /// enable/disable barcode type: Matrix 2of5
@@ -526,17 +777,12 @@ impl BarcodeScanner {
         min_length: Option<u8>,
         max_length: Option<u8>,
     ) -> Result<()> {
-        if enable {
-            if let Some(min_length) = min_length {
-                self.send_write_command(0x0047, &[min_length])?;
-            }
-            if let Some(max_length) = max_length {
-                self.send_write_command(0x0048, &[max_length])?;
-            }
-            self.send_write_command(0x0046, &[0x01])
-        } else {
-            self.send_write_command(0x0046, &[0x00])
-        }
+        self.allow_barcode_type(
+            Symbology::Matrix2of5.enable_address(),
+            enable.into(),
+            min_length,
+            max_length,
+        )
     }
 
     /// enable/disable barcode type: Industrial
@@ -549,17 +795,12 @@ impl BarcodeScanner {
         min_length: Option<u8>,
         max_length: Option<u8>,
     ) -> Result<()> {
-        if enable {
-            if let Some(min_length) = min_length {
-                self.send_write_command(0x0044, &[min_length])?;
-            }
-            if let Some(max_length) = max_length {
-                self.send_write_command(0x0045, &[max_length])?;
-            }
-            self.send_write_command(0x0043, &[0x01])
-        } else {
-            self.send_write_command(0x0043, &[0x00])
-        }
+        self.allow_barcode_type(
+            Symbology::Industrial2of5.enable_address(),
+            enable.into(),
+            min_length,
+            max_length,
+        )
     }
 
     /// enable/disable barcode type: CodeBar
@@ -573,21 +814,14 @@ impl BarcodeScanner {
         min_length: Option<u8>,
         max_length: Option<u8>,
     ) -> Result<()> {
-        if enable {
-            if let Some(min_length) = min_length {
-                self.send_write_command(0x003D, &[min_length])?;
-            }
-            if let Some(max_length) = max_length {
-                self.send_write_command(0x003E, &[max_length])?;
-            }
-            let mut mode: u8 = 0x01;
-            if with_start_stop_character {
-                mode |= 0x02;
-            }
-            self.send_write_command(0x003C, &[mode])
-        } else {
-            self.send_write_command(0x003C, &[0x00])
-        }
+        let mode =
+            SymbologyEnable::for_symbology(Symbology::Codabar, enable, with_start_stop_character);
+        self.allow_barcode_type(
+            Symbology::Codabar.enable_address(),
+            mode.encode(),
+            min_length,
+            max_length,
+        )
     }
 
     /// enable/disable barcode type: Code128
@@ -600,17 +834,12 @@ impl BarcodeScanner {
         min_length: Option<u8>,
         max_length: Option<u8>,
     ) -> Result<()> {
-        if enable {
-            if let Some(min_length) = min_length {
-                self.send_write_command(0x0034, &[min_length])?;
-            }
-            if let Some(max_length) = max_length {
-                self.send_write_command(0x0035, &[max_length])?;
-            }
-            self.send_write_command(0x0033, &[0x01])
-        } else {
-            self.send_write_command(0x0033, &[0x00])
-        }
+        self.allow_barcode_type(
+            Symbology::Code128.enable_address(),
+            enable.into(),
+            min_length,
+            max_length,
+        )
     }
 
     /// enable/disable barcode type: Code39
@@ -623,17 +852,12 @@ impl BarcodeScanner {
         min_length: Option<u8>,
         max_length: Option<u8>,
     ) -> Result<()> {
-        if enable {
-            if let Some(min_length) = min_length {
-                self.send_write_command(0x0037, &[min_length])?;
-            }
-            if let Some(max_length) = max_length {
-                self.send_write_command(0x0038, &[max_length])?;
-            }
-            self.send_write_command(0x0036, &[0x01])
-        } else {
-            self.send_write_command(0x0036, &[0x00])
-        }
+        self.allow_barcode_type(
+            Symbology::Code39.enable_address(),
+            enable.into(),
+            min_length,
+            max_length,
+        )
     }
 
     /// enable/disable barcode type: Code93
@@ -646,107 +870,82 @@ impl BarcodeScanner {
         min_length: Option<u8>,
         max_length: Option<u8>,
     ) -> Result<()> {
-        if enable {
-            if let Some(min_length) = min_length {
-                self.send_write_command(0x003A, &[min_length])?;
-            }
-            if let Some(max_length) = max_length {
-                self.send_write_command(0x003B, &[max_length])?;
-            }
-            self.send_write_command(0x0039, &[0x01])
-        } else {
-            self.send_write_command(0x0039, &[0x00])
-        }
+        self.allow_barcode_type(
+            Symbology::Code93.enable_address(),
+            enable.into(),
+            min_length,
+            max_length,
+        )
     }
 
     /// enable/disable barcode type: UPCA
     pub fn allow_upca(&mut self, enable: bool) -> Result<()> {
-        if enable {
-            self.send_write_command(0x0030, &[0x01])
-        } else {
-            self.send_write_command(0x0030, &[0x00])
-        }
+        self.allow_barcode_type(Symbology::UPCA.enable_address(), enable.into(), None, None)
     }
 
     /// enable/disable barcode type: UPCE0
     pub fn allow_upce0(&mut self, enable: bool) -> Result<()> {
-        if enable {
-            self.send_write_command(0x0031, &[0x01])
-        } else {
-            self.send_write_command(0x0031, &[0x00])
-        }
+        self.allow_barcode_type(Symbology::UPCE0.enable_address(), enable.into(), None, None)
     }
 
     /// enable/disable barcode type: UPCE1
     pub fn allow_upce1(&mut self, enable: bool) -> Result<()> {
-        if enable {
-            self.send_write_command(0x0032, &[0x01])
-        } else {
-            self.send_write_command(0x0032, &[0x00])
-        }
+        self.allow_barcode_type(Symbology::UPCE1.enable_address(), enable.into(), None, None)
     }
 
     /// enable/disable barcode type: PDF417
     pub fn allow_pdf417(&mut self, enable: bool) -> Result<()> {
-        if enable {
-            self.send_write_command(0x0055, &[0x01])
-        } else {
-            self.send_write_command(0x0055, &[0x00])
-        }
+        self.allow_barcode_type(
+            Symbology::PDF417.enable_address(),
+            enable.into(),
+            None,
+            None,
+        )
     }
 
     /// enable/disable barcode type: Micro PDF417
     pub fn allow_micro_pdf417(&mut self, enable: bool) -> Result<()> {
-        if enable {
-            self.send_write_command(0x0029, &[0x01])
-        } else {
-            self.send_write_command(0x0029, &[0x00])
-        }
+        self.allow_barcode_type(
+            Symbology::MicroPDF417.enable_address(),
+            enable.into(),
+            None,
+            None,
+        )
     }
 
     /// enable/disable barcode type: ISBN
     pub fn allow_micro_isbn(&mut self, enable: bool) -> Result<()> {
-        if enable {
-            self.send_write_command(0x0057, &[0x01])
-        } else {
-            self.send_write_command(0x0057, &[0x00])
-        }
+        self.allow_barcode_type(Symbology::ISBN.enable_address(), enable.into(), None, None)
     }
 
     /// enable/disable barcode type: ISSN
     pub fn allow_micro_issn(&mut self, enable: bool) -> Result<()> {
-        if enable {
-            self.send_write_command(0x0056, &[0x01])
-        } else {
-            self.send_write_command(0x0056, &[0x00])
-        }
+        self.allow_barcode_type(Symbology::ISSN.enable_address(), enable.into(), None, None)
     }
 
     /// enable/disable barcode type: Dot Matrix code
     pub fn allow_dotmatrix(&mut self, enable: bool) -> Result<()> {
-        if enable {
-            self.send_write_command(0x0054, &[0x01])
-        } else {
-            self.send_write_command(0x0054, &[0x00])
-        }
+        self.allow_barcode_type(
+            Symbology::DotMatrix.enable_address(),
+            enable.into(),
+            None,
+            None,
+        )
     }
 
     /// enable/disable barcode type: QR code
     pub fn allow_qr(&mut self, enable: bool) -> Result<()> {
-        if enable {
-            self.send_write_command(0x003F, &[0x01])
-        } else {
-            self.send_write_command(0x003F, &[0x00])
-        }
+        self.allow_barcode_type(Symbology::QR.enable_address(), enable.into(), None, None)
     }
 
     /// enable/disable barcode type: Micro QR
     pub fn allow_microqr(&mut self, enable: bool) -> Result<()> {
-        if enable {
-            self.send_write_command(0x005F, &[0x01])
-        } else {
-            self.send_write_command(0x005F, &[0x00])
-        }
+        self.allow_barcode_type(
+            Symbology::MicroQR.enable_address(),
+            enable.into(),
+            None,
+            None,
+        )
     }
 
     /// enable/disable barcode type: Interleaved 2 of 5
@@ -759,55 +958,72 @@ impl BarcodeScanner {
         min_length: Option<u8>,
         max_length: Option<u8>,
     ) -> Result<()> {
-        if enable {
-            if let Some(min_length) = min_length {
-                self.send_write_command(0x0041, &[min_length])?;
-            }
-            if let Some(max_length) = max_length {
-                self.send_write_command(0x0042, &[max_length])?;
-            }
-            self.send_write_command(0x0040, &[0x01])
-        } else {
-            self.send_write_command(0x0040, &[0x00])
-        }
+        self.allow_barcode_type(
+            Symbology::Interleaved2of5.enable_address(),
+            enable.into(),
+            min_length,
+            max_length,
+        )
+    }
+
+    /// return if a barcode type is enabled, together with its length limits
+    ///
+    /// # Arguments
+    ///
+    /// * `symbology` the barcode type to check
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use waveshare_barcodescanner::{Symbology, interface::BarcodeScanner};
+    ///
+    /// fn main() -> Result<()> {
+    ///     let mut scanner = BarcodeScanner::new("/dev/serial0")?;
+    ///     let status = scanner.is_allowed(Symbology::Code128)?;
+    ///     println!(
+    ///         "Code128 enabled: {}, length {:?} to {:?}",
+    ///         status.enabled, status.min_length, status.max_length
+    ///     );
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn is_allowed(&mut self, symbology: Symbology) -> Result<SymbologyStatus> {
+        let mut buffer: Vec<u8> = vec![0x00; if symbology.has_length_limits() { 3 } else { 1 }];
+        self.read_registers_into(symbology.enable_address(), &mut buffer)?;
+        decode_symbology_status(symbology, &buffer)
     }
 
     /// disable setting changes via barcode scanning (seems like a really good idea for production use)
     pub fn disable_setting_scanning(&mut self) -> Result<()> {
-        let mut buffer: [u8; 1] = [0x00; 1];
-        let bytes_read = self.send_read_command_fixed_reply(0x0003, &mut buffer)?;
-        if bytes_read != 1 {
-            return Err(anyhow!(
-                "incorrect number of bytes read from device, expected 1 received {}",
-                bytes_read
-            ));
-        }
-        buffer[0] &= 0xfe;
-        buffer[0] |= 0x02;
-        self.send_write_command(0x0003, &buffer)?;
-        Ok(())
+        let setting_code: SettingCode = self.read_register()?;
+        self.write_register(&SettingCode {
+            enabled: false,
+            output_content: false,
+            ..setting_code
+        })
     }
 
     /// enable setting changes via barcode scanning
     pub fn enable_setting_scanning(&mut self) -> Result<()> {
-        let mut buffer: [u8; 1] = [0x00; 1];
-        let bytes_read = self.send_read_command_fixed_reply(0x0003, &mut buffer)?;
-        if bytes_read != 1 {
-            return Err(anyhow!(
-                "incorrect number of bytes read from device, expected 1 received {}",
-                bytes_read
-            ));
-        }
-        buffer[0] &= 0xfc;
-        self.send_write_command(0x0003, &buffer)?;
-        Ok(())
+        let setting_code: SettingCode = self.read_register()?;
+        self.write_register(&SettingCode {
+            enabled: true,
+            output_content: false,
+            ..setting_code
+        })
+    }
+
+    /// return true if setting changes via barcode scanning are enabled
+    pub fn is_setting_scanning_enabled(&mut self) -> Result<bool> {
+        Ok(self.read_register::<SettingCode>()?.enabled)
     }
 
     /// save all pending changes to flash
     pub fn save_to_flash(&mut self) -> Result<()> {
-        self.send_to_serial(0x09, 1, 0x0000, None, Some(&[0x00]))?;
+        let command = encode_command(FUNCTION_SAVE, 1, 0x0000, None, Some(&[0x00]))?;
         let mut buffer: [u8; 1] = [0x00; 1];
-        self.read_from_serial_command_reply(&mut buffer)?;
+        self.exchange(&command, &mut buffer)?;
         Ok(())
     }
 
@@ -817,12 +1033,169 @@ impl BarcodeScanner {
     ///
     /// Make sure to afterwards enable `UART` mode via the corresponding barcode again.
     pub fn factory_reset(&mut self) -> Result<()> {
-        self.send_to_serial(0x08, 1, 0x00D9, None, Some(&[0x50]))?;
+        let command = encode_command(
+            FUNCTION_WRITE,
+            1,
+            FACTORY_RESET,
+            None,
+            Some(&[FACTORY_RESET_VALUE]),
+        )?;
         let mut buffer: [u8; 1] = [0x00; 1];
-        self.read_from_serial_command_reply(&mut buffer)?;
+        self.exchange(&command, &mut buffer)?;
         Ok(())
     }
 
+    /// read a register and convert it into its typed value
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use waveshare_barcodescanner::{interface::BarcodeScanner, registers::ResultOptions};
+    ///
+    /// fn main() -> Result<()> {
+    ///     let mut scanner = BarcodeScanner::new("/dev/serial0")?;
+    ///     let options: ResultOptions = scanner.read_register()?;
+    ///     println!("end character: {:?}", options.end_character);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn read_register<R: Register>(&mut self) -> Result<R> {
+        let mut buffer: [u8; 1] = [0x00; 1];
+        self.read_registers_into(R::ADDRESS, &mut buffer)?;
+        Ok(R::decode(buffer[0]))
+    }
+
+    /// write the typed value of a register
+    ///
+    /// see [`BarcodeScanner::write_registers`] for registers which should not be written directly
+    pub fn write_register<R: Register>(&mut self, value: &R) -> Result<()> {
+        self.send_write_command(R::ADDRESS, &[value.encode()])
+    }
+
+    /// read `length` consecutive registers starting at `address`
+    ///
+    /// Gives access to registers without a dedicated method in this crate. The reply is
+    /// verified by its checksum, the retry policy applies.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` the first register to read
+    /// * `length` the number of registers to read, 1 to 256
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use waveshare_barcodescanner::interface::BarcodeScanner;
+    ///
+    /// fn main() -> Result<()> {
+    ///     let mut scanner = BarcodeScanner::new("/dev/serial0")?;
+    ///     let registers = scanner.read_registers(0x0000, 16)?;
+    ///     println!("registers 0x0000-0x000F: {:02X?}", registers);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn read_registers(&mut self, address: u16, length: usize) -> Result<Vec<u8>> {
+        if length == 0 {
+            return Err(Error::InvalidArgument(String::from(
+                "at least one register has to be read",
+            )));
+        }
+        let mut values: Vec<u8> = vec![0x00; length];
+        self.read_registers_into(address, &mut values)?;
+        Ok(values)
+    }
+
+    /// write `values` to consecutive registers starting at `address`
+    ///
+    /// Gives access to registers without a dedicated method in this crate. The retry policy
+    /// applies, call [`BarcodeScanner::save_to_flash`] to keep the values after a power cycle.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` the first register to write
+    /// * `values` the values to write, 1 to 255 bytes
+    ///
+    /// # Note
+    ///
+    /// Writing the barcode result options (0x0060) or the baud rate (0x002A-0x002B) this way
+    /// leaves the scanner unable to decode barcodes or to talk to the device, use
+    /// [`BarcodeScanner::set_protocol_output`] and [`BarcodeScanner::set_baud_rate`] instead.
+    pub fn write_registers(&mut self, address: u16, values: &[u8]) -> Result<()> {
+        if values.is_empty() {
+            return Err(Error::InvalidArgument(String::from(
+                "at least one register has to be written",
+            )));
+        }
+        self.send_write_command(address, values)
+    }
+
+    /// send a command packet with any function type and return the data of the reply
+    ///
+    /// The packet is built from the arguments and gets the header and the checksum added,
+    /// the reply is verified by its checksum. A reply length of 0 is returned as 256 bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `function_type` [`crate::protocol::FUNCTION_READ`], [`FUNCTION_WRITE`] or [`FUNCTION_SAVE`]
+    /// * `address` the register address the command applies to
+    /// * `data` the data of the command, for reads the number of registers (0 for 256)
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use waveshare_barcodescanner::{interface::BarcodeScanner, protocol::FUNCTION_READ};
+    ///
+    /// fn main() -> Result<()> {
+    ///     let mut scanner = BarcodeScanner::new("/dev/serial0")?;
+    ///     let reply = scanner.send_raw_command(FUNCTION_READ, 0x00E1, &[0x01])?;
+    ///     println!("hardware version register: {:02X?}", reply);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn send_raw_command(
+        &mut self,
+        function_type: u8,
+        address: u16,
+        data: &[u8],
+    ) -> Result<Vec<u8>> {
+        let command = encode_raw_command(function_type, address, data)?;
+        let mut buffer: Vec<u8> = vec![0x00; 256];
+        let bytes_read = self.exchange(&command, &mut buffer)?;
+        buffer.truncate(bytes_read);
+        Ok(buffer)
+    }
+
+    /// send a packet exactly as given and return the fields of the reply
+    ///
+    /// Unlike [`BarcodeScanner::send_raw_command`] the packet is neither built nor checked,
+    /// and a reply with a non-zero status is returned instead of [`Error::DeviceRejected`].
+    /// The checksum of the reply is not verified either, use [`ReplyFrame::checksum_ok`] to check it.
+    /// The command is not repeated on failure. Use [`crate::protocol::complete_command`] to add
+    /// the checksum to a packet.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use anyhow::Result;
+    /// use waveshare_barcodescanner::{interface::BarcodeScanner, protocol::complete_command};
+    ///
+    /// fn main() -> Result<()> {
+    ///     let mut scanner = BarcodeScanner::new("/dev/serial0")?;
+    ///     let command = complete_command(&[0x7E, 0x00, 0x07, 0x01, 0x00, 0xE1, 0x01])?;
+    ///     let reply = scanner.send_raw_frame(&command)?;
+    ///     println!("{}", reply);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn send_raw_frame(&mut self, packet: &[u8]) -> Result<ReplyFrame> {
+        self.write_to_serial(packet)?;
+        let reply = self.read_reply_packet(ReplyDecoder::without_checksum())?;
+        ReplyFrame::decode(&reply)
+    }
+
     /// send a read command and return the reply from the barcode scanner
     ///
     /// used for an expected payload size upon read
@@ -831,47 +1204,82 @@ impl BarcodeScanner {
         address: u16,
         return_data: &mut [u8],
     ) -> Result<usize> {
-        self.send_to_serial(0x07, 0x01, address, Some(return_data.len()), None)?;
-        self.read_from_serial_command_reply(return_data)
+        let command = encode_read_command(address, return_data.len())?;
+        self.exchange(&command, return_data)
+    }
+
+    /// read `values.len()` consecutive registers starting at `address`
+    pub(crate) fn read_registers_into(&mut self, address: u16, values: &mut [u8]) -> Result<()> {
+        let bytes_read = self.send_read_command_fixed_reply(address, values)?;
+        if bytes_read != values.len() {
+            return Err(Error::UnexpectedLength {
+                expected: values.len(),
+                received: bytes_read,
+            });
+        }
+        Ok(())
     }
 
     /// send a write command to the barcode scanner
-    fn send_write_command(&mut self, address: u16, data: &[u8]) -> Result<()> {
-        self.send_to_serial(0x08, data.len().try_into()?, address, None, Some(data))?;
+    pub(crate) fn send_write_command(&mut self, address: u16, data: &[u8]) -> Result<()> {
+        let command = encode_write_command(address, data)?;
         let mut buffer: [u8; 1] = [0x00; 1];
-        self.read_from_serial_command_reply(&mut buffer)?;
+        self.exchange(&command, &mut buffer)?;
         Ok(())
     }
 
-    /// write a command packet to the barcode scanner
-    fn send_to_serial(
+    /// enable/disable a barcode type and optionally set its length limits
+    pub(crate) fn allow_barcode_type(
         &mut self,
-        function_type: u8,
-        length: u8,
-        address: u16,
-        return_data_length: Option<usize>,
-        write_data: Option<&[u8]>,
+        enable_address: u16,
+        mode: u8,
+        min_length: Option<u8>,
+        max_length: Option<u8>,
     ) -> Result<()> {
-        let mut buffer: Vec<u8> = Vec::new();
-        buffer.push(0x7e);
-        buffer.push(0x00);
-        buffer.push(function_type);
-        buffer.push(length);
-        buffer.append(&mut address.to_be_bytes().to_vec());
-        if let Some(return_data_length) = return_data_length {
-            // special case, the barcode scanner expects a length of 0 when 256 bytes should be returned
-            if return_data_length == 256 {
-                buffer.push(0x00);
-            } else {
-                buffer.push(return_data_length.try_into()?);
-            }
+        for (address, value) in barcode_type_writes(enable_address, mode, min_length, max_length) {
+            self.send_write_command(address, &[value])?;
         }
-        if let Some(write_data) = write_data {
-            buffer.append(&mut write_data.to_vec());
+        Ok(())
+    }
+
+    /// send a command packet and read the reply packet, repeating it per the retry policy
+    ///
+    /// the `read_data` array needs to be allocated to the exact size of payload data expected
+    fn exchange(&mut self, command: &[u8], read_data: &mut [u8]) -> Result<usize> {
+        let mut attempt: u32 = 1;
+        loop {
+            let result = self
+                .write_to_serial(command)
+                .and_then(|_| self.read_from_serial_command_reply(read_data));
+            match result {
+                Err(err) if self.retry_policy.should_retry(attempt, &err) => {
+                    let delay = self.retry_policy.delay(attempt);
+                    debug!(
+                        "attempt {} of {} failed: {}, retrying in {:?}",
+                        attempt,
+                        self.retry_policy.get_max_attempts(),
+                        err,
+                        delay
+                    );
+                    std::thread::sleep(delay);
+                    // drop the remainder of the failed reply
+                    self.port.discard_buffers()?;
+                    attempt += 1;
+                }
+                result => {
+                    if attempt > 1 {
+                        debug!("command completed after {} retries", attempt - 1);
+                    }
+                    return result;
+                }
+            }
         }
-        buffer.append(&mut calculate_crc(&buffer[2..])?.to_be_bytes().to_vec());
+    }
+
+    /// write a complete packet to the barcode scanner
+    fn write_to_serial(&mut self, buffer: &[u8]) -> Result<()> {
         debug!("WRITE TO SERIAL {} bytes: {:02X?}", buffer.len(), buffer);
-        self.port.write_all(&buffer)?;
+        self.port.write_all(buffer)?;
         Ok(())
     }
 
@@ -879,40 +1287,104 @@ impl BarcodeScanner {
     ///
     /// the `read_data` array needs to be allocated to the exact size of payload data expected
     fn read_from_serial_command_reply(&mut self, read_data: &mut [u8]) -> Result<usize> {
-        debug!("reading {} bytes", read_data.len() + 6);
-        let mut buffer: Vec<u8> = (0..read_data.len() + 6).map(|_| 0x00).collect();
-        self.port.read_exact(&mut buffer)?;
-        debug!("READ FROM SERIAL {} bytes: {:02X?}", buffer.len(), buffer);
-        if buffer.len() < 6 {
-            // not enough data read
-            return Err(anyhow!(
-                "read data is shorter than expected, got {} expected {} bytes",
-                buffer.len(),
-                read_data.len()
-            ));
-        }
-        if buffer[0] != 0x02 || buffer[1] != 0x00 {
-            // invalid header
-            return Err(anyhow!("invalid header received"));
-        }
-        if buffer[2] != 0x00 {
-            // operation was not successful
-            return Err(anyhow!(
-                "barcode scanner indicates an unsuccessful operation, rc: {}",
-                buffer[2]
-            ));
-        }
-        // verify the checksum
-        let received_checksum =
-            ((buffer[buffer.len() - 2] as u16) << 8) | buffer[buffer.len() - 1] as u16;
-        verify_crc(&buffer[2..buffer.len() - 2], received_checksum)?;
-        let mut data_length: usize = buffer[3] as usize;
-        // special case, the barcode scanner returns a length of 0 when 256 bytes have been returned
-        if data_length == 0 {
-            data_length = 256;
-        }
-        read_data[..data_length as usize].copy_from_slice(&buffer[4..data_length + 4]);
-        Ok(data_length)
+        debug!("reading {} bytes", read_data.len() + REPLY_OVERHEAD);
+        let packet = self.read_reply_packet(ReplyDecoder::new())?;
+        decode_reply(&packet, read_data)
+    }
+
+    /// read a complete reply packet from the barcode scanner using `decoder`
+    ///
+    /// data in front of the reply is kept if it belongs to a barcode, all other data is dropped,
+    /// [`Error::Timeout`] is returned if no reply arrives within [`reply_deadline`]
+    fn read_reply_packet(&mut self, decoder: ReplyDecoder) -> Result<Vec<u8>> {
+        let result = self.read_reply_packet_until_deadline(decoder);
+        self.drop_incomplete_unsolicited_data();
+        result
+    }
+
+    /// read a complete reply packet, see [`BarcodeScanner::read_reply_packet`]
+    fn read_reply_packet_until_deadline(&mut self, mut decoder: ReplyDecoder) -> Result<Vec<u8>> {
+        let deadline = Instant::now() + reply_deadline(self.port.read_timeout()?);
+        let mut buffer: [u8; 2] = [0x00; 2];
+        loop {
+            if Instant::now() >= deadline {
+                debug!("no reply received before the deadline");
+                return Err(Error::Timeout);
+            }
+            self.port.read_exact(&mut buffer[..1])?;
+            let mut received = &buffer[..1];
+            if self.protocol_output && decoder.is_idle() && buffer[0] == SCAN_FRAME_HEADER[0] {
+                // in continuous or sensing mode scan frames might arrive before the reply
+                self.port.read_exact(&mut buffer[1..])?;
+                if buffer[1] == SCAN_FRAME_HEADER[1] {
+                    self.keep_unsolicited_scan_frame()?;
+                    continue;
+                }
+                // not a scan frame, the second byte might start the reply
+                received = &buffer[..];
+            }
+            for &byte in received {
+                match decoder.push(byte) {
+                    None => {}
+                    Some(Ok(DecoderEvent::Garbage(garbage))) => {
+                        for byte in garbage {
+                            self.keep_unsolicited_data(byte);
+                        }
+                    }
+                    Some(Ok(DecoderEvent::Reply(packet))) => {
+                        debug!("READ FROM SERIAL {} bytes: {:02X?}", packet.len(), packet);
+                        return Ok(packet);
+                    }
+                    Some(Err(err)) => return Err(err),
+                }
+            }
+        }
+    }
+
+    /// buffer the rest of a scan frame received in front of a command reply
+    fn keep_unsolicited_scan_frame(&mut self) -> Result<()> {
+        let mut frame: Vec<u8> = vec![SCAN_FRAME_HEADER[0], SCAN_FRAME_HEADER[1], 0x00];
+        self.port.read_exact(&mut frame[2..])?;
+        frame.resize(scan_frame_data_length(frame[2]) + SCAN_FRAME_OVERHEAD, 0x00);
+        self.port.read_exact(&mut frame[3..])?;
+        trace!("buffering unsolicited scan frame {:02X?}", frame);
+        self.unsolicited_data.extend(frame);
+        Ok(())
+    }
+
+    /// buffer a byte received in front of a command reply if it belongs to a barcode
+    ///
+    /// in continuous or sensing mode barcode data might arrive before the reply,
+    /// it starts with a known code ID and ends with CR
+    fn keep_unsolicited_data(&mut self, byte: u8) {
+        let inside_barcode = self
+            .unsolicited_data
+            .back()
+            .is_some_and(|&byte| byte != 0x0D);
+        if !self.protocol_output && (inside_barcode || is_known_code_id(byte)) {
+            trace!("buffering unsolicited barcode data {:02X}", byte);
+            self.unsolicited_data.push_back(byte);
+        } else {
+            debug!("dropping unexpected data {:02X}", byte);
+        }
+    }
+
+    /// drop buffered barcode data which is not terminated by CR
+    ///
+    /// called after waiting for a reply, so buffered data always ends with a complete barcode
+    fn drop_incomplete_unsolicited_data(&mut self) {
+        if self.protocol_output {
+            return;
+        }
+        let complete = self
+            .unsolicited_data
+            .iter()
+            .rposition(|&byte| byte == 0x0D)
+            .map_or(0, |position| position + 1);
+        if complete < self.unsolicited_data.len() {
+            let dropped: Vec<u8> = self.unsolicited_data.drain(complete..).collect();
+            debug!("dropping unexpected data {:02X?}", dropped);
+        }
     }
 
     /// read a full packet from the barcode scanner
@@ -925,6 +1397,18 @@ impl BarcodeScanner {
         read_data: &mut [u8],
         timeout: Option<Duration>,
     ) -> Result<()> {
+        // use barcode data buffered while waiting for a command reply first
+        let buffered = read_data.len().min(self.unsolicited_data.len());
+        for (target, source) in read_data
+            .iter_mut()
+            .zip(self.unsolicited_data.drain(..buffered))
+        {
+            *target = source;
+        }
+        let read_data = &mut read_data[buffered..];
+        if read_data.is_empty() {
+            return Ok(());
+        }
         let start_ts = Instant::now();
         loop {
             debug!("reading {} bytes", read_data.len());
@@ -953,38 +1437,453 @@ impl BarcodeScanner {
         }
     }
 
-    /// read the next line of recognized data from the barcode scanner
+    /// waits for a scan frame (protocol output mode) for at most `timeout` and decodes it
     ///
-    /// will wait until EOL or end of data
-    fn read_line_from_serial(&mut self) -> Result<(Vec<u8>, bool)> {
-        let mut result: Vec<u8> = Vec::with_capacity(256);
+    /// data in front of the scan frame header is dropped
+    fn read_scan_frame_within(&mut self, timeout: Duration) -> Result<Option<ScanEvent>> {
+        let deadline = Instant::now() + timeout;
+        let mut frame: Vec<u8> = SCAN_FRAME_HEADER.to_vec();
+        let mut byte: [u8; 1] = [0x00; 1];
+        let mut header_start = false;
         loop {
-            let mut buffer: [u8; 1] = [0; 1];
-            debug!("reading {} bytes", buffer.len());
-            match self.port.read(&mut buffer) {
-                Ok(0) => {
-                    return Err(anyhow!("no data was read from the device"));
+            if header_start {
+                // the second header byte has to follow right away
+                self.read_from_serial_exact(&mut byte, None)?;
+                if byte[0] == SCAN_FRAME_HEADER[1] {
+                    break;
                 }
-                Ok(read_bytes) => {
-                    trace!(
-                        "READ FROM SERIAL {} bytes: {:02X?}",
-                        read_bytes,
-                        &buffer[..read_bytes]
-                    );
-                    if buffer[0] == 0x0A {
-                        trace!("EOL detected, result len {}", result.len());
-                        return Ok((result, false));
-                    }
-                    if buffer[0] == 0x0D {
-                        trace!("end of data detected, result len {}", result.len());
-                        return Ok((result, true));
-                    }
-                    result.append(&mut buffer[..read_bytes].to_vec());
+                debug!("dropping unexpected data {:02X}", SCAN_FRAME_HEADER[0]);
+            } else {
+                byte[0] = 0x00;
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                self.read_from_serial_exact(&mut byte, Some(remaining))?;
+                if byte[0] == 0x00 {
+                    // no data received until the timout was reached
+                    debug!("timeout waiting for a scan frame");
+                    return Ok(None);
                 }
-                Err(err) => {
-                    return Err(err.into());
+            }
+            header_start = byte[0] == SCAN_FRAME_HEADER[0];
+            if !header_start {
+                debug!("dropping unexpected data {:02X}", byte[0]);
+                if Instant::now() >= deadline {
+                    debug!("timeout waiting for a scan frame");
+                    return Ok(None);
                 }
             }
         }
+        frame.push(0x00);
+        self.read_from_serial_exact(&mut frame[2..], None)?;
+        frame.resize(scan_frame_data_length(frame[2]) + SCAN_FRAME_OVERHEAD, 0x00);
+        self.read_from_serial_exact(&mut frame[3..], None)?;
+        let (code_id, payload) = decode_scan_frame(&frame)?;
+        self.scan_event(code_id, payload)
+    }
+
+    /// decode the payload of a scan, the latency is measured from the last scan trigger
+    fn scan_event(&mut self, code_id: u8, payload: Vec<u8>) -> Result<Option<ScanEvent>> {
+        let scan = ScanEvent::decode(code_id, payload, self.aim_id_prefix, self.triggered_at)?;
+        if scan.is_some() {
+            self.triggered_at = None;
+        }
+        Ok(scan)
+    }
+
+    /// read the payload of a barcode from the barcode scanner
+    ///
+    /// the payload ends with a CR which is not followed by more data within `END_OF_DATA_GAP`,
+    /// so no byte of the payload itself is treated as a terminator
+    fn read_payload_from_serial(&mut self) -> Result<Vec<u8>> {
+        let mut result: Vec<u8> = Vec::with_capacity(256);
+        loop {
+            let byte = self.read_byte_from_serial()?;
+            result.push(byte);
+            if byte == 0x0D && !self.data_follows()? {
+                // remove the end character
+                result.pop();
+                trace!("end of data detected, result len {}", result.len());
+                return Ok(result);
+            }
+        }
+    }
+
+    /// read the next byte of barcode data, waiting at most the read timeout of the transport
+    fn read_byte_from_serial(&mut self) -> Result<u8> {
+        if let Some(byte) = self.unsolicited_data.pop_front() {
+            return Ok(byte);
+        }
+        let mut buffer: [u8; 1] = [0; 1];
+        match self.port.read(&mut buffer)? {
+            0 => Err(Error::Io(std::io::ErrorKind::UnexpectedEof.into())),
+            read_bytes => {
+                trace!(
+                    "READ FROM SERIAL {} bytes: {:02X?}",
+                    read_bytes,
+                    &buffer[..read_bytes]
+                );
+                Ok(buffer[0])
+            }
+        }
+    }
+
+    /// check if more barcode data arrives within `END_OF_DATA_GAP`
+    ///
+    /// a received byte is kept for the next read
+    fn data_follows(&mut self) -> Result<bool> {
+        if !self.unsolicited_data.is_empty() {
+            // buffered data always ends with a CR (incomplete data is dropped after each reply),
+            // anything after it belongs to the next barcode
+            return Ok(false);
+        }
+        let read_timeout = self.port.read_timeout()?;
+        self.port.set_read_timeout(END_OF_DATA_GAP)?;
+        let mut buffer: [u8; 1] = [0; 1];
+        let result = self.port.read(&mut buffer);
+        self.port.set_read_timeout(read_timeout)?;
+        match result {
+            Ok(0) => Ok(false),
+            Ok(_) => {
+                trace!("data after CR: {:02X}", buffer[0]);
+                self.unsolicited_data.push_front(buffer[0]);
+                Ok(true)
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::TimedOut => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::{Barcode, crc::calculate_crc, protocol::encode_scan_frame};
+
+    /// in-memory transport replaying canned device output
+    #[derive(Default)]
+    struct FakeTransport {
+        input: VecDeque<u8>,
+        written: Vec<u8>,
+        /// byte returned endlessly once the input is exhausted
+        noise: Option<u8>,
+    }
+
+    impl FakeTransport {
+        fn with_input(input: &[u8]) -> Self {
+            FakeTransport {
+                input: input.iter().copied().collect(),
+                written: Vec::new(),
+                noise: None,
+            }
+        }
+    }
+
+    impl Transport for FakeTransport {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if let (true, Some(noise)) = (self.input.is_empty(), self.noise) {
+                buf.fill(noise);
+                return Ok(buf.len());
+            }
+            if self.input.is_empty() {
+                return Err(std::io::ErrorKind::TimedOut.into());
+            }
+            let count = buf.len().min(self.input.len());
+            for (target, source) in buf.iter_mut().zip(self.input.drain(..count)) {
+                *target = source;
+            }
+            Ok(count)
+        }
+
+        fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+            self.written.extend_from_slice(buf);
+            Ok(())
+        }
+
+        fn set_read_timeout(&mut self, _timeout: Duration) -> std::io::Result<()> {
+            Ok(())
+        }
+
+        fn read_timeout(&self) -> std::io::Result<Duration> {
+            // keep the reply deadline short while the line is flooded
+            match self.noise {
+                Some(_) => Ok(Duration::from_millis(1)),
+                None => Ok(Duration::from_millis(100)),
+            }
+        }
+
+        fn discard_buffers(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// reply frame for a successful write command
+    const WRITE_OK: [u8; 7] = [0x02, 0x00, 0x00, 0x01, 0x00, 0x33, 0x31];
+
+    #[test]
+    fn test_with_transport_sets_output_format() -> Result<()> {
+        let scanner = BarcodeScanner::with_transport(FakeTransport::with_input(&WRITE_OK))?;
+        let port = scanner.into_transport();
+        assert_eq!(
+            port.written,
+            [0x7E, 0x00, 0x08, 0x01, 0x00, 0x60, 0x05, 0x2F, 0x16]
+        );
+        assert!(port.input.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_hw_version() -> Result<()> {
+        let mut scanner = BarcodeScanner::with_transport(FakeTransport::with_input(&WRITE_OK))?;
+        let mut reply = vec![0x02, 0x00, 0x00, 0x01, 0x8C];
+        reply.extend_from_slice(&calculate_crc(&reply[2..])?.to_be_bytes());
+        scanner.transport_mut().input.extend(reply);
+        assert_eq!(scanner.get_hw_version()?, "V1.40");
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_barcode() -> Result<()> {
+        let mut scanner = BarcodeScanner::with_transport(FakeTransport::with_input(&WRITE_OK))?;
+        scanner.transport_mut().input.extend(b"j0123456789\r");
+        match scanner.read_barcode()?.map(|scan| scan.barcode) {
+            Some(Barcode::Code128(data)) => assert_eq!(data, "0123456789"),
+            _ => panic!("expected a Code128 barcode"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_scan_frame() -> Result<()> {
+        let mut scanner = BarcodeScanner::with_transport(FakeTransport::with_input(&WRITE_OK))?;
+        scanner.transport_mut().input.extend(WRITE_OK);
+        scanner.set_protocol_output(true)?;
+        assert_eq!(
+            scanner.transport().written[9..],
+            [0x7E, 0x00, 0x08, 0x01, 0x00, 0x60, 0x84, 0xAE, 0xBF]
+        );
+        let frame = encode_scan_frame(0x6A, b"01\r23")?;
+        scanner.transport_mut().input.extend(&frame);
+        match scanner.read_barcode()?.map(|scan| scan.barcode) {
+            Some(Barcode::Code128(data)) => assert_eq!(data, b"01\r23"),
+            _ => panic!("expected a Code128 barcode"),
+        }
+        // a corrupted frame is detected by its checksum
+        let mut corrupted = frame.clone();
+        corrupted[4] = b'7';
+        scanner.transport_mut().input.extend(corrupted);
+        assert!(matches!(
+            scanner.read_barcode(),
+            Err(Error::ChecksumMismatch { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_resynchronize_scan_frame() -> Result<()> {
+        let mut scanner = BarcodeScanner::with_transport(FakeTransport::with_input(&WRITE_OK))?;
+        scanner.transport_mut().input.extend(WRITE_OK);
+        scanner.set_protocol_output(true)?;
+        // junk and a header byte without its second byte in front of the frame
+        scanner
+            .transport_mut()
+            .input
+            .extend([0x41, 0xFF, 0x03, 0x41, 0x03]);
+        scanner
+            .transport_mut()
+            .input
+            .extend(encode_scan_frame(0x6A, b"0123")?);
+        match scanner.read_barcode()?.map(|scan| scan.barcode) {
+            Some(Barcode::Code128(data)) => assert_eq!(data, b"0123"),
+            _ => panic!("expected a Code128 barcode"),
+        }
+        assert!(scanner.transport().input.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_header() -> Result<()> {
+        let mut scanner = BarcodeScanner::with_transport(FakeTransport::with_input(&WRITE_OK))?;
+        scanner
+            .transport_mut()
+            .input
+            .extend([0x03, 0x00, 0x00, 0x01, 0x00, 0x33, 0x31]);
+        assert!(matches!(scanner.start_scan(), Err(Error::Timeout)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_resynchronize_reply() -> Result<()> {
+        let mut scanner = BarcodeScanner::with_transport(FakeTransport::with_input(&WRITE_OK))?;
+        // line noise and a broken header in front of the reply
+        scanner
+            .transport_mut()
+            .input
+            .extend([0x00, 0xFF, 0x02, 0x01, 0x80]);
+        scanner.transport_mut().input.extend(WRITE_OK);
+        scanner.start_scan()?;
+        assert!(scanner.transport().input.is_empty());
+        // barcode data in front of the reply is kept
+        scanner.transport_mut().input.extend(b"j0123\r");
+        scanner.transport_mut().input.extend(WRITE_OK);
+        scanner.stop_scan()?;
+        match scanner.read_barcode()?.map(|scan| scan.barcode) {
+            Some(Barcode::Code128(data)) => assert_eq!(data, "0123"),
+            _ => panic!("expected a Code128 barcode"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_noise_before_reply() -> Result<()> {
+        let mut scanner = BarcodeScanner::with_transport(FakeTransport::with_input(&WRITE_OK))?;
+        // 0x42 is a known code ID, but the data is not terminated by CR
+        scanner.transport_mut().input.extend([0x41, 0x42]);
+        scanner.transport_mut().input.extend(WRITE_OK);
+        scanner.start_scan()?;
+        assert!(scanner.unsolicited_data.is_empty());
+        // the tail of an earlier reply does not start a barcode
+        scanner.transport_mut().input.extend([0x33, 0x31]);
+        scanner.transport_mut().input.extend(WRITE_OK);
+        scanner.stop_scan()?;
+        assert!(scanner.unsolicited_data.is_empty());
+        // the next barcode is not prefixed with the noise
+        scanner.transport_mut().input.extend(b"j0123\r");
+        match scanner.read_barcode()?.map(|scan| scan.barcode) {
+            Some(Barcode::Code128(data)) => assert_eq!(data, "0123"),
+            _ => panic!("expected a Code128 barcode"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_noise_reply_deadline() -> Result<()> {
+        let mut scanner = BarcodeScanner::with_transport(FakeTransport::with_input(&WRITE_OK))?;
+        scanner.transport_mut().noise = Some(b'A');
+        assert!(matches!(scanner.start_scan(), Err(Error::Timeout)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_stray_scan_frame_header() -> Result<()> {
+        let mut scanner = BarcodeScanner::with_transport(FakeTransport::with_input(&WRITE_OK))?;
+        scanner.transport_mut().input.extend(WRITE_OK);
+        scanner.set_protocol_output(true)?;
+        // a stray scan frame header byte directly followed by the reply
+        scanner.transport_mut().input.push_back(0x03);
+        scanner.transport_mut().input.extend(WRITE_OK);
+        scanner.start_scan()?;
+        // a stray scan frame header byte followed by noise
+        scanner.transport_mut().input.extend([0x03, 0x41]);
+        scanner.transport_mut().input.extend(WRITE_OK);
+        scanner.stop_scan()?;
+        assert!(scanner.transport().input.is_empty());
+        // nothing was mistaken for a scan frame
+        scanner
+            .transport_mut()
+            .input
+            .extend(encode_scan_frame(0x6A, b"01")?);
+        match scanner.read_barcode()?.map(|scan| scan.barcode) {
+            Some(Barcode::Code128(data)) => assert_eq!(data, b"01"),
+            _ => panic!("expected a Code128 barcode"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_checksum_mismatch() -> Result<()> {
+        let mut scanner = BarcodeScanner::with_transport(FakeTransport::with_input(&WRITE_OK))?;
+        scanner
+            .transport_mut()
+            .input
+            .extend([0x02, 0x00, 0x00, 0x01, 0x00, 0x33, 0x32]);
+        assert!(matches!(
+            scanner.start_scan(),
+            Err(Error::ChecksumMismatch {
+                expected: 0x3332,
+                calculated: 0x3331
+            })
+        ));
+        // the next reply is decoded again
+        scanner.transport_mut().input.extend(WRITE_OK);
+        scanner.start_scan()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_raw_frame_checksum_mismatch() -> Result<()> {
+        let mut scanner = BarcodeScanner::with_transport(FakeTransport::with_input(&WRITE_OK))?;
+        scanner
+            .transport_mut()
+            .input
+            .extend([0x02, 0x00, 0x00, 0x01, 0x00, 0x33, 0x32]);
+        let reply = scanner.send_raw_frame(&encode_read_command(0x0000, 1)?)?;
+        assert!(reply.is_success());
+        assert!(!reply.checksum_ok());
+        assert_eq!(reply.calculated_checksum, 0x3331);
+        assert!(reply.to_string().ends_with("(mismatch, calculated 3331)"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_retry_policy() -> Result<()> {
+        let mut scanner = BarcodeScanner::with_transport(FakeTransport::with_input(&WRITE_OK))?;
+        scanner.set_retry_policy(RetryPolicy::new(3).backoff(Duration::from_millis(1)));
+        // the first reply is corrupted, the second one is lost
+        scanner
+            .transport_mut()
+            .input
+            .extend([0x02, 0x00, 0x00, 0x01, 0x00, 0x33, 0x32]);
+        assert!(matches!(scanner.start_scan(), Err(Error::Timeout)));
+        assert_eq!(scanner.transport().written.len(), 9 * 4);
+        // the command is sent again until a valid reply is received
+        scanner
+            .transport_mut()
+            .input
+            .extend([0x02, 0x00, 0x00, 0x01, 0x00, 0x33, 0x32]);
+        scanner.transport_mut().input.extend(WRITE_OK);
+        scanner.start_scan()?;
+        assert_eq!(scanner.transport().written.len(), 9 * 6);
+        // errors not covered by the policy are returned immediately
+        let mut reply = vec![0x02, 0x00, 0x01, 0x01, 0x00];
+        reply.extend_from_slice(&calculate_crc(&reply[2..])?.to_be_bytes());
+        scanner.transport_mut().input.extend(reply);
+        assert!(matches!(scanner.stop_scan(), Err(Error::DeviceRejected(1))));
+        assert_eq!(scanner.transport().written.len(), 9 * 7);
+        Ok(())
+    }
+
+    #[test]
+    fn test_device_rejected() -> Result<()> {
+        let mut scanner = BarcodeScanner::with_transport(FakeTransport::with_input(&WRITE_OK))?;
+        let mut reply = vec![0x02, 0x00, 0x01, 0x01, 0x00];
+        reply.extend_from_slice(&calculate_crc(&reply[2..])?.to_be_bytes());
+        scanner.transport_mut().input.extend(reply);
+        assert!(matches!(
+            scanner.start_scan(),
+            Err(Error::DeviceRejected(1))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_code_id() -> Result<()> {
+        let mut scanner = BarcodeScanner::with_transport(FakeTransport::with_input(&WRITE_OK))?;
+        scanner.transport_mut().input.extend(b"~0123\r");
+        match scanner.read_barcode()?.map(|scan| scan.barcode) {
+            Some(Barcode::Unknown(code_id, data)) => {
+                assert_eq!(code_id, 0x7E);
+                assert_eq!(data, b"0123");
+            }
+            _ => panic!("expected an unknown barcode"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_reply_timeout() -> Result<()> {
+        let mut scanner = BarcodeScanner::with_transport(FakeTransport::with_input(&WRITE_OK))?;
+        assert!(matches!(scanner.start_scan(), Err(Error::Timeout)));
+        Ok(())
     }
 }
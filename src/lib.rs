@@ -1,12 +1,36 @@
 use std::fmt::Display;
 
+#[cfg(feature = "async")]
+pub mod async_interface;
+pub mod background;
+pub mod builder;
+pub mod capture;
+pub mod config;
 pub mod crc;
+pub mod error;
+#[cfg(any(test, feature = "simulator"))]
+pub mod fault;
 pub mod interface;
+pub mod payload;
+#[cfg(feature = "serde")]
+pub mod profile;
+pub mod protocol;
+pub mod registers;
+pub mod retry;
+pub mod scan;
+#[cfg(any(test, feature = "simulator"))]
+pub mod simulator;
+pub mod transport;
+
+pub use error::{Error, Result};
+pub use payload::Payload;
 
 // indicates, that the checksum was not calculated (checksum will not be validated)
 pub const IGNORED_CHECKSUM: u16 = 0xabcd;
 
 /// target light mode to indicate scanning
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TargetLightMode {
     /// target light is disabled
     AlwaysOff,
@@ -17,6 +41,8 @@ pub enum TargetLightMode {
 }
 
 /// light mode for object detection in dark environments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IlluminationMode {
     /// white LED is disabled (scanning in dark environments might be difficult)
     AlwaysOff,
@@ -27,6 +53,8 @@ pub enum IlluminationMode {
 }
 
 /// scanner mode operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OperationMode {
     /// push button to scan
     Manual,
@@ -39,6 +67,8 @@ pub enum OperationMode {
 }
 
 /// scan area for bar codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ScanArea {
     /// the entire area of view of the camera is used to detect barcodes
     All,
@@ -47,6 +77,8 @@ pub enum ScanArea {
 }
 
 /// type of barcodes to enable/disable (device dependent)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Barcodes {
     /// enable all supported barcodes
     EnableAll,
@@ -56,58 +88,258 @@ pub enum Barcodes {
     Default,
 }
 
+/// mode of operation and light/buzzer parameters (register 0x0000)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScannerMode {
+    /// the LED on the circuit board lights up shortly after a successful scan
+    pub led_indication: bool,
+    /// a successful scan is confirmed with a short tone
+    pub buzzer: bool,
+    /// how the green target light is used
+    pub target_light_mode: TargetLightMode,
+    /// how the white LED light operates
+    pub illumination_mode: IlluminationMode,
+    /// the scanning mode
+    pub operation_mode: OperationMode,
+}
+
+/// barcode type which can be enabled/disabled on the barcode scanner
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Symbology {
+    /// International Article Number - EAN13
+    EAN13,
+    /// International Article Number - EAN8
+    EAN8,
+    /// Universal Product Code - UPC-A
+    UPCA,
+    /// Universal Product Code - UPC-E0
+    UPCE0,
+    /// Universal Product Code - UPC-E1
+    UPCE1,
+    /// Code 128
+    Code128,
+    /// Code 39
+    Code39,
+    /// Code 93
+    Code93,
+    /// Codabar
+    Codabar,
+    /// QR code
+    QR,
+    /// Interleaved 2of5
+    Interleaved2of5,
+    /// Industrial 2of5
+    Industrial2of5,
+    /// Matrix 2of5
+    Matrix2of5,
+    /// Code 11
+    Code11,
+    /// MSI-Plessey
+    MSI,
+    /// GS1 Databar (RSS-14)
+    GS1Databar,
+    /// GS1 Databar Limited (RSS)
+    GS1DatabarLimited,
+    /// GS1 Databar Expanded (RSS)
+    GS1DatabarExpanded,
+    /// GS1 Databar Stacked (RSS)
+    GS1DatabarStacked,
+    /// PDF417
+    PDF417,
+    /// Micro PDF417
+    MicroPDF417,
+    /// International Standard Book Number
+    ISBN,
+    /// International Standard Serial Number
+    ISSN,
+    /// Dot Matrix code
+    DotMatrix,
+    /// mini QR code
+    MicroQR,
+}
+
+impl Symbology {
+    /// all barcode types which can be enabled/disabled
+    pub const ALL: [Symbology; 25] = [
+        Symbology::EAN13,
+        Symbology::EAN8,
+        Symbology::UPCA,
+        Symbology::UPCE0,
+        Symbology::UPCE1,
+        Symbology::Code128,
+        Symbology::Code39,
+        Symbology::Code93,
+        Symbology::Codabar,
+        Symbology::QR,
+        Symbology::Interleaved2of5,
+        Symbology::Industrial2of5,
+        Symbology::Matrix2of5,
+        Symbology::Code11,
+        Symbology::MSI,
+        Symbology::GS1Databar,
+        Symbology::GS1DatabarLimited,
+        Symbology::GS1DatabarExpanded,
+        Symbology::GS1DatabarStacked,
+        Symbology::PDF417,
+        Symbology::MicroPDF417,
+        Symbology::ISBN,
+        Symbology::ISSN,
+        Symbology::DotMatrix,
+        Symbology::MicroQR,
+    ];
+
+    /// return the address of the register enabling the barcode type
+    pub fn enable_address(self) -> u16 {
+        match self {
+            Symbology::EAN13 => registers::EAN13_ENABLE,
+            Symbology::EAN8 => registers::EAN8_ENABLE,
+            Symbology::UPCA => registers::UPCA_ENABLE,
+            Symbology::UPCE0 => registers::UPCE0_ENABLE,
+            Symbology::UPCE1 => registers::UPCE1_ENABLE,
+            Symbology::Code128 => registers::CODE128_ENABLE,
+            Symbology::Code39 => registers::CODE39_ENABLE,
+            Symbology::Code93 => registers::CODE93_ENABLE,
+            Symbology::Codabar => registers::CODABAR_ENABLE,
+            Symbology::QR => registers::QR_ENABLE,
+            Symbology::Interleaved2of5 => registers::INTERLEAVED_2OF5_ENABLE,
+            Symbology::Industrial2of5 => registers::INDUSTRIAL_2OF5_ENABLE,
+            Symbology::Matrix2of5 => registers::MATRIX_2OF5_ENABLE,
+            Symbology::Code11 => registers::CODE11_ENABLE,
+            Symbology::MSI => registers::MSI_ENABLE,
+            Symbology::GS1Databar => registers::GS1_DATABAR_ENABLE,
+            Symbology::GS1DatabarLimited => registers::GS1_DATABAR_LIMITED_ENABLE,
+            Symbology::GS1DatabarExpanded => registers::GS1_DATABAR_EXPANDED_ENABLE,
+            Symbology::GS1DatabarStacked => registers::GS1_DATABAR_STACKED_ENABLE,
+            Symbology::PDF417 => registers::PDF417_ENABLE,
+            Symbology::MicroPDF417 => registers::MICRO_PDF417_ENABLE,
+            Symbology::ISBN => registers::ISBN_ENABLE,
+            Symbology::ISSN => registers::ISSN_ENABLE,
+            Symbology::DotMatrix => registers::DOT_MATRIX_ENABLE,
+            Symbology::MicroQR => registers::MICRO_QR_ENABLE,
+        }
+    }
+
+    /// true if minimum and maximum length registers follow the enable register
+    pub fn has_length_limits(self) -> bool {
+        matches!(
+            self,
+            Symbology::Code128
+                | Symbology::Code39
+                | Symbology::Code93
+                | Symbology::Codabar
+                | Symbology::Interleaved2of5
+                | Symbology::Industrial2of5
+                | Symbology::Matrix2of5
+                | Symbology::Code11
+                | Symbology::MSI
+                | Symbology::GS1DatabarExpanded
+        )
+    }
+}
+
+/// enable state and length limits of a barcode type
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct SymbologyStatus {
+    /// true if the barcode type is recognized
+    pub enabled: bool,
+    /// Codabar only: the start/stop characters are part of the payload
+    pub start_stop_character: bool,
+    /// minimum number of characters of a valid barcode (barcode types with length limits)
+    pub min_length: Option<u8>,
+    /// maximum number of characters of a valid barcode (barcode types with length limits)
+    pub max_length: Option<u8>,
+}
+
 /// scanned barcode
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Barcode {
     /// Interleaved 2of5, single line of digits
-    Interleaved2of5(String),
+    Interleaved2of5(Payload),
+    /// Industrial 2of5, single line of digits
+    Industrial2of5(Payload),
+    /// Matrix 2of5, single line of digits
+    Matrix2of5(Payload),
     /// International Article Number - EAN13, single line of digits
-    EAN13(String),
+    EAN13(Payload),
+    /// International Article Number - EAN8, single line of digits
+    EAN8(Payload),
+    /// Universal Product Code - UPC-A, single line of digits
+    UPCA(Payload),
+    /// Universal Product Code - UPC-E0 or UPC-E1, single line of digits
+    UPCE(Payload),
+    /// International Standard Book Number, single line of digits
+    ISBN(Payload),
+    /// International Standard Serial Number, single line of digits
+    ISSN(Payload),
     /// Code 128, single line of alphanumeric characters
-    Code128(String),
+    Code128(Payload),
     /// Code 39, single line of alphanumeric characters
-    Code39(String),
+    Code39(Payload),
+    /// Code 93, single line of alphanumeric characters
+    Code93(Payload),
+    /// Code 11, single line of digits
+    Code11(Payload),
+    /// Codabar, single line of digits (optionally with start/stop characters)
+    Codabar(Payload),
+    /// MSI-Plessey, single line of digits
+    MSI(Payload),
+    /// GS1 Databar (RSS-14, limited, expanded or stacked), single line of alphanumeric characters
+    GS1Databar(Payload),
+    /// PDF417, multiple lines of alphanumeric characters
+    PDF417(Payload),
+    /// Micro PDF417, multiple lines of alphanumeric characters
+    MicroPDF417(Payload),
     /// QR code, multiple lines of alphanumeric characters
-    QR(Vec<String>),
+    QR(Payload),
     /// mini QR code, multiple lines of alphanumeric characters
-    MicroQR(Vec<String>),
+    MicroQR(Payload),
     /// Dot Matrix code, multiple lines of alphanumeric characters
-    DotMatrix(Vec<String>),
+    DotMatrix(Payload),
+    /// barcode type not known to this crate, with its code ID and the raw payload
+    Unknown(u8, Payload),
+}
+
+/// format the lines of a multi line barcode, prefixed with the line number
+fn format_lines(data: &Payload) -> String {
+    data.lines()
+        .enumerate()
+        .map(|(i, line)| format!("{}: {}", i, String::from_utf8_lossy(line)))
+        .collect::<Vec<String>>()
+        .join("\n")
 }
 
 impl Display for Barcode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Barcode::Interleaved2of5(data) => write!(f, "interleaved2of5: {}", data),
+            Barcode::Industrial2of5(data) => write!(f, "industrial2of5: {}", data),
+            Barcode::Matrix2of5(data) => write!(f, "matrix2of5: {}", data),
             Barcode::EAN13(data) => write!(f, "EAN13: {}", data),
+            Barcode::EAN8(data) => write!(f, "EAN8: {}", data),
+            Barcode::UPCA(data) => write!(f, "UPC-A: {}", data),
+            Barcode::UPCE(data) => write!(f, "UPC-E: {}", data),
+            Barcode::ISBN(data) => write!(f, "ISBN: {}", data),
+            Barcode::ISSN(data) => write!(f, "ISSN: {}", data),
             Barcode::Code39(data) => write!(f, "Code39: {}", data),
+            Barcode::Code93(data) => write!(f, "Code93: {}", data),
+            Barcode::Code11(data) => write!(f, "Code11: {}", data),
             Barcode::Code128(data) => write!(f, "Code128: {}", data),
-            Barcode::QR(data) => write!(
-                f,
-                "QR: {}",
-                data.iter()
-                    .enumerate()
-                    .map(|(i, line)| format!("{}: {}", i, line))
-                    .collect::<Vec<String>>()
-                    .join("\n")
-            ),
-            Barcode::MicroQR(data) => write!(
-                f,
-                "Micro QR: {}",
-                data.iter()
-                    .enumerate()
-                    .map(|(i, line)| format!("{}: {}", i, line))
-                    .collect::<Vec<String>>()
-                    .join("\n")
-            ),
-            Barcode::DotMatrix(data) => write!(
-                f,
-                "Dot Matrix: {}",
-                data.iter()
-                    .enumerate()
-                    .map(|(i, line)| format!("{}: {}", i, line))
-                    .collect::<Vec<String>>()
-                    .join("\n")
-            ),
+            Barcode::Codabar(data) => write!(f, "Codabar: {}", data),
+            Barcode::MSI(data) => write!(f, "MSI: {}", data),
+            Barcode::GS1Databar(data) => write!(f, "GS1 Databar: {}", data),
+            Barcode::PDF417(data) => write!(f, "PDF417: {}", format_lines(data)),
+            Barcode::MicroPDF417(data) => write!(f, "Micro PDF417: {}", format_lines(data)),
+            Barcode::QR(data) => write!(f, "QR: {}", format_lines(data)),
+            Barcode::MicroQR(data) => write!(f, "Micro QR: {}", format_lines(data)),
+            Barcode::DotMatrix(data) => write!(f, "Dot Matrix: {}", format_lines(data)),
+            Barcode::Unknown(code_id, data) => {
+                write!(f, "unknown {:02X}: {:02X?}", code_id, data.as_bytes())
+            }
         }
     }
 }
@@ -0,0 +1,146 @@
+use std::fmt::Display;
+
+use crate::{Error, Result};
+
+/// raw data of a scanned barcode, exactly as sent by the barcode scanner
+///
+/// The barcode scanner does not tell which character encoding the barcode uses, use one of the
+/// decoding helpers matching the barcodes in your application.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Payload(Vec<u8>);
+
+impl Payload {
+    /// wrap the raw bytes of a barcode
+    pub fn new(data: Vec<u8>) -> Self {
+        Payload(data)
+    }
+
+    /// return the raw bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// consume the payload and return the raw bytes
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+
+    /// return the number of bytes
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// return true if there are no bytes
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// return the non-empty lines (separated by LF) of a multi line barcode
+    pub fn lines(&self) -> impl Iterator<Item = &[u8]> {
+        self.0
+            .split(|&byte| byte == 0x0A)
+            .filter(|line| !line.is_empty())
+    }
+
+    /// decode the payload as UTF-8, failing on invalid data
+    pub fn to_utf8(&self) -> Result<String> {
+        String::from_utf8(self.0.clone())
+            .map_err(|err| Error::InvalidData(format!("payload is not valid UTF-8: {}", err)))
+    }
+
+    /// decode the payload as UTF-8, replacing invalid data with U+FFFD
+    pub fn to_utf8_lossy(&self) -> String {
+        String::from_utf8_lossy(&self.0).into_owned()
+    }
+
+    /// decode the payload as Latin-1 (ISO 8859-1), every byte is a valid character
+    pub fn to_latin1(&self) -> String {
+        self.0.iter().map(|&c| c as char).collect()
+    }
+
+    /// decode the payload as GB18030 (a superset of GBK), failing on invalid data
+    pub fn to_gb18030(&self) -> Result<String> {
+        encoding_rs::GB18030
+            .decode_without_bom_handling_and_without_replacement(&self.0)
+            .map(|text| text.into_owned())
+            .ok_or_else(|| Error::InvalidData(String::from("payload is not valid GB18030")))
+    }
+}
+
+impl Display for Payload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.0))
+    }
+}
+
+impl From<Vec<u8>> for Payload {
+    fn from(data: Vec<u8>) -> Self {
+        Payload(data)
+    }
+}
+
+impl From<&[u8]> for Payload {
+    fn from(data: &[u8]) -> Self {
+        Payload(data.to_vec())
+    }
+}
+
+impl AsRef<[u8]> for Payload {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl PartialEq<[u8]> for Payload {
+    fn eq(&self, other: &[u8]) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&[u8]> for Payload {
+    fn eq(&self, other: &&[u8]) -> bool {
+        self.0 == *other
+    }
+}
+
+impl<const N: usize> PartialEq<&[u8; N]> for Payload {
+    fn eq(&self, other: &&[u8; N]) -> bool {
+        self.0 == other[..]
+    }
+}
+
+impl PartialEq<&str> for Payload {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decoding() -> Result<()> {
+        let payload = Payload::from("Grüße".as_bytes());
+        assert_eq!(payload.to_utf8()?, "Grüße");
+        assert_eq!(payload.to_latin1(), "GrÃ¼Ã\u{9f}e");
+        assert_eq!(Payload::from(&[0x47, 0x72, 0xFC][..]).to_latin1(), "Grü");
+        assert!(Payload::from(&[0x47, 0x72, 0xFC][..]).to_utf8().is_err());
+        assert_eq!(
+            Payload::from(&[0xC4, 0xE3, 0xBA, 0xC3][..]).to_gb18030()?,
+            "你好"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_lines() {
+        let payload = Payload::from(&b"first\n\nsecond\r\x00third\n"[..]);
+        assert_eq!(
+            payload.lines().collect::<Vec<&[u8]>>(),
+            [&b"first"[..], &b"second\r\x00third"[..]]
+        );
+    }
+}
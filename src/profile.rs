@@ -0,0 +1,337 @@
+#[cfg(any(feature = "toml", feature = "json"))]
+use std::path::Path;
+use std::{collections::BTreeMap, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(any(feature = "toml", feature = "json"))]
+use crate::Error;
+use crate::{
+    Barcodes, Result, ScanArea, ScannerMode, Symbology, SymbologyStatus, interface::BarcodeScanner,
+    registers::SymbologyEnable, transport::Transport,
+};
+
+/// document describing the settings of a barcode scanner, e.g. to keep it in version control
+///
+/// All settings are optional, only the settings present in a profile are applied. Profiles
+/// can be stored as TOML (feature `toml`) or JSON (feature `json`).
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use waveshare_barcodescanner::{interface::BarcodeScanner, profile::ScannerProfile};
+///
+/// fn main() -> Result<()> {
+///     let mut old_scanner = BarcodeScanner::new("/dev/ttyUSB0")?;
+///     let profile = ScannerProfile::from_scanner(&mut old_scanner)?;
+///     let mut new_scanner = BarcodeScanner::new("/dev/ttyUSB1")?;
+///     profile.apply_profile(&mut new_scanner)?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScannerProfile {
+    /// mode of operation and light/buzzer parameters
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<ScannerMode>,
+    /// area of the camera view where barcodes are detected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scan_area: Option<ScanArea>,
+    /// barcode types recognized in general
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub barcodes: Option<Barcodes>,
+    /// scan timeout of the device in milliseconds (100ms resolution), 0 for no timeout
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scan_timeout_ms: Option<u64>,
+    /// allow setting changes via barcode scanning
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub setting_scanning: Option<bool>,
+    /// enable state and length limits per barcode type
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub symbologies: BTreeMap<Symbology, SymbologyStatus>,
+    /// save the settings to flash after applying them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub save_to_flash: Option<bool>,
+}
+
+impl ScannerProfile {
+    /// read all settings of the barcode scanner into a profile
+    pub fn from_scanner<T: Transport>(scanner: &mut BarcodeScanner<T>) -> Result<Self> {
+        let (scan_area, barcodes) = scanner.get_scan_area_and_barcodes()?;
+        let mut symbologies = BTreeMap::new();
+        for symbology in Symbology::ALL {
+            symbologies.insert(symbology, scanner.is_allowed(symbology)?);
+        }
+        Ok(ScannerProfile {
+            mode: Some(scanner.get_mode()?),
+            scan_area: Some(scan_area),
+            barcodes: Some(barcodes),
+            scan_timeout_ms: Some(scanner.get_scan_timeout()?.as_millis() as u64),
+            setting_scanning: Some(scanner.is_setting_scanning_enabled()?),
+            symbologies,
+            save_to_flash: None,
+        })
+    }
+
+    /// send the settings of the profile to the barcode scanner
+    ///
+    /// # Arguments
+    ///
+    /// * `scanner` the barcode scanner to configure
+    pub fn apply_profile<T: Transport>(&self, scanner: &mut BarcodeScanner<T>) -> Result<()> {
+        if let Some(mode) = self.mode {
+            scanner.set_mode(
+                mode.led_indication,
+                mode.buzzer,
+                mode.target_light_mode,
+                mode.illumination_mode,
+                mode.operation_mode,
+            )?;
+        }
+        if self.scan_area.is_some() || self.barcodes.is_some() {
+            // both are stored in the same register, keep the one not in the profile
+            let (scan_area, barcodes) = scanner.get_scan_area_and_barcodes()?;
+            scanner.set_scan_area_and_barcodes(
+                self.scan_area.unwrap_or(scan_area),
+                self.barcodes.unwrap_or(barcodes),
+            )?;
+        }
+        match self.scan_timeout_ms {
+            Some(0) => scanner.disable_scan_timeout()?,
+            Some(scan_timeout_ms) => {
+                scanner.set_scan_timeout(Duration::from_millis(scan_timeout_ms))?
+            }
+            None => {}
+        }
+        match self.setting_scanning {
+            Some(true) => scanner.enable_setting_scanning()?,
+            Some(false) => scanner.disable_setting_scanning()?,
+            None => {}
+        }
+        for (&symbology, status) in &self.symbologies {
+            let mode = SymbologyEnable::for_symbology(
+                symbology,
+                status.enabled,
+                status.start_stop_character,
+            );
+            let (min_length, max_length) = if symbology.has_length_limits() {
+                (status.min_length, status.max_length)
+            } else {
+                (None, None)
+            };
+            scanner.allow_barcode_type(
+                symbology.enable_address(),
+                mode.encode(),
+                min_length,
+                max_length,
+            )?;
+        }
+        if self.save_to_flash == Some(true) {
+            scanner.save_to_flash()?;
+        }
+        Ok(())
+    }
+
+    /// parse a profile from TOML
+    #[cfg(feature = "toml")]
+    pub fn from_toml(text: &str) -> Result<Self> {
+        toml::from_str(text).map_err(|err| Error::InvalidData(format!("invalid profile: {}", err)))
+    }
+
+    /// format the profile as TOML
+    #[cfg(feature = "toml")]
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(self)
+            .map_err(|err| Error::InvalidArgument(format!("unable to format profile: {}", err)))
+    }
+
+    /// parse a profile from JSON
+    #[cfg(feature = "json")]
+    pub fn from_json(text: &str) -> Result<Self> {
+        serde_json::from_str(text)
+            .map_err(|err| Error::InvalidData(format!("invalid profile: {}", err)))
+    }
+
+    /// format the profile as JSON
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|err| Error::InvalidArgument(format!("unable to format profile: {}", err)))
+    }
+
+    /// load a profile from a file, the format is selected by the extension (`.toml` or `.json`)
+    ///
+    /// # Examples
+    ///
+    /// A profile in TOML format, barcode types not listed are left unchanged:
+    ///
+    /// ```toml
+    /// scan_timeout_ms = 3000
+    /// setting_scanning = false
+    /// save_to_flash = true
+    ///
+    /// [mode]
+    /// led_indication = true
+    /// buzzer = false
+    /// target_light_mode = "Standard"
+    /// illumination_mode = "Standard"
+    /// operation_mode = "Command"
+    ///
+    /// [symbologies.Code128]
+    /// enabled = true
+    /// min_length = 8
+    ///
+    /// [symbologies.QR]
+    /// enabled = false
+    /// ```
+    #[cfg(any(feature = "toml", feature = "json"))]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let text = std::fs::read_to_string(path.as_ref())?;
+        match Format::of(path.as_ref())? {
+            #[cfg(feature = "toml")]
+            Format::Toml => Self::from_toml(&text),
+            #[cfg(feature = "json")]
+            Format::Json => Self::from_json(&text),
+        }
+    }
+
+    /// save the profile to a file, the format is selected by the extension (`.toml` or `.json`)
+    #[cfg(any(feature = "toml", feature = "json"))]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let text = match Format::of(path.as_ref())? {
+            #[cfg(feature = "toml")]
+            Format::Toml => self.to_toml()?,
+            #[cfg(feature = "json")]
+            Format::Json => self.to_json()?,
+        };
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+/// file formats supported for profiles
+#[cfg(any(feature = "toml", feature = "json"))]
+enum Format {
+    #[cfg(feature = "toml")]
+    Toml,
+    #[cfg(feature = "json")]
+    Json,
+}
+
+#[cfg(any(feature = "toml", feature = "json"))]
+impl Format {
+    /// select the file format based on the extension of `path`
+    fn of(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            #[cfg(feature = "toml")]
+            Some("toml") => Ok(Format::Toml),
+            #[cfg(feature = "json")]
+            Some("json") => Ok(Format::Json),
+            _ => Err(Error::InvalidArgument(format!(
+                "unsupported profile format: {}",
+                path.display()
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IlluminationMode, OperationMode, TargetLightMode, simulator::SimulatedScanner};
+
+    fn profile() -> ScannerProfile {
+        let mut symbologies = BTreeMap::new();
+        symbologies.insert(
+            Symbology::Code128,
+            SymbologyStatus {
+                enabled: true,
+                min_length: Some(8),
+                max_length: Some(12),
+                ..Default::default()
+            },
+        );
+        symbologies.insert(Symbology::QR, SymbologyStatus::default());
+        ScannerProfile {
+            mode: Some(ScannerMode {
+                led_indication: false,
+                buzzer: true,
+                target_light_mode: TargetLightMode::AlwaysOff,
+                illumination_mode: IlluminationMode::AlwaysOn,
+                operation_mode: OperationMode::Command,
+            }),
+            scan_area: Some(ScanArea::Center),
+            scan_timeout_ms: Some(3000),
+            setting_scanning: Some(false),
+            symbologies,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_apply_profile() -> Result<()> {
+        let mut scanner = BarcodeScanner::with_transport(SimulatedScanner::new())?;
+        let profile = profile();
+        profile.apply_profile(&mut scanner)?;
+        let applied = ScannerProfile::from_scanner(&mut scanner)?;
+        assert_eq!(applied.mode, profile.mode);
+        assert_eq!(applied.scan_area, Some(ScanArea::Center));
+        assert_eq!(applied.barcodes, Some(Barcodes::Default));
+        assert_eq!(applied.scan_timeout_ms, Some(3000));
+        assert_eq!(applied.setting_scanning, Some(false));
+        assert_eq!(
+            applied.symbologies[&Symbology::Code128],
+            profile.symbologies[&Symbology::Code128]
+        );
+        assert!(!applied.symbologies[&Symbology::QR].enabled);
+        assert!(applied.symbologies[&Symbology::EAN13].enabled);
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_scan_timeout_round_trip() -> Result<()> {
+        let mut scanner = BarcodeScanner::with_transport(SimulatedScanner::new())?;
+        scanner.write_registers(0x0006, &[0x00])?;
+        let profile = ScannerProfile::from_scanner(&mut scanner)?;
+        assert_eq!(profile.scan_timeout_ms, Some(0));
+        let mut new_scanner = BarcodeScanner::with_transport(SimulatedScanner::new())?;
+        profile.apply_profile(&mut new_scanner)?;
+        assert_eq!(new_scanner.transport().register(0x0006), 0x00);
+        assert_eq!(ScannerProfile::from_scanner(&mut new_scanner)?, profile);
+        Ok(())
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_toml() -> Result<()> {
+        let profile = profile();
+        assert_eq!(ScannerProfile::from_toml(&profile.to_toml()?)?, profile);
+        assert!(ScannerProfile::from_toml("scan_timeout_ms = \"soon\"").is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json() -> Result<()> {
+        let profile = profile();
+        assert_eq!(ScannerProfile::from_json(&profile.to_json()?)?, profile);
+        Ok(())
+    }
+
+    #[cfg(all(feature = "toml", feature = "json"))]
+    #[test]
+    fn test_load_and_save() -> Result<()> {
+        let profile = profile();
+        for name in ["profile.toml", "profile.json"] {
+            let path = std::env::temp_dir().join(format!("{}-{}", std::process::id(), name));
+            profile.save(&path)?;
+            let loaded = ScannerProfile::load(&path);
+            std::fs::remove_file(&path)?;
+            assert_eq!(loaded?, profile);
+        }
+        assert!(profile.save("profile.yaml").is_err());
+        Ok(())
+    }
+}
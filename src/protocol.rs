@@ -0,0 +1,972 @@
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use tracing::debug;
+
+use crate::{
+    Barcode, Error, Payload, Result, Symbology, SymbologyStatus,
+    crc::{calculate_crc, verify_crc},
+    registers::{EndCharacter, Register, ResultOptions, SymbologyEnable},
+};
+
+/// barcode result options (register 0x0060) expected by the barcode payload decoding
+///
+/// * without protocol
+/// * CR as end of line
+/// * without RF
+/// * without prefix
+/// * with CodeID (0x04)
+/// * without suffix
+/// * with end character (0x01)
+pub const BARCODE_RESULT_OPTIONS: u8 = 0x04 | 0x01;
+
+/// barcode result options (register 0x0060) expected by the scan frame decoding
+///
+/// * with protocol (0x80)
+/// * without RF
+/// * without prefix
+/// * with CodeID (0x04)
+/// * without suffix
+/// * without end character
+pub const PROTOCOL_RESULT_OPTIONS: u8 = 0x80 | 0x04;
+
+/// time without further data after a CR which marks the end of a barcode payload
+///
+/// a CR followed by more data within this time is part of the payload (e.g. in QR codes)
+pub const END_OF_DATA_GAP: Duration = Duration::from_millis(20);
+
+/// check if barcode result options (register 0x0060) can be decoded by this crate
+///
+/// the code ID needs to be enabled without prefix or suffix, plain text output additionally
+/// needs CR as end character
+pub fn output_options_supported(options: u8) -> bool {
+    let options = ResultOptions::decode(options);
+    if !options.code_id || options.prefix || options.suffix {
+        return false;
+    }
+    options.protocol || (options.end_character_enabled && options.end_character == EndCharacter::CR)
+}
+
+/// number of bytes a reply packet adds to the payload data (header, status, length, checksum)
+pub const REPLY_OVERHEAD: usize = 6;
+
+/// number of bytes of the largest reply packet (256 bytes of payload data)
+pub const MAX_REPLY_LENGTH: usize = 256 + REPLY_OVERHEAD;
+
+/// number of bytes tolerated in front of a reply packet before waiting for it is given up
+const REPLY_DEADLINE_MARGIN: usize = 64;
+
+/// longest time to wait for a complete reply packet
+///
+/// a reply is given up after as many read timeouts as the largest reply packet has bytes
+/// (plus a margin), so noise on the line can not block a command forever
+pub fn reply_deadline(read_timeout: Duration) -> Duration {
+    read_timeout * (MAX_REPLY_LENGTH + REPLY_DEADLINE_MARGIN) as u32
+}
+
+/// supported baud rates and the divider written to the baud rate registers (0x002A-0x002B)
+pub const BAUD_RATES: [(u32, u16); 9] = [
+    (1200, 0x09C4),
+    (2400, 0x04E2),
+    (4800, 0x0271),
+    (9600, 0x0139),
+    (14400, 0x00D0),
+    (19200, 0x009C),
+    (38400, 0x0068),
+    (57600, 0x0034),
+    (115200, 0x001A),
+];
+
+/// build the value of the baud rate registers (low byte at 0x002A, high byte at 0x002B)
+pub fn encode_baud_rate(baud_rate: u32) -> Result<[u8; 2]> {
+    BAUD_RATES
+        .iter()
+        .find(|(rate, _)| *rate == baud_rate)
+        .map(|(_, divider)| divider.to_le_bytes())
+        .ok_or_else(|| Error::InvalidArgument(format!("unsupported baud rate {}", baud_rate)))
+}
+
+/// convert the value of the baud rate registers (low byte at 0x002A, high byte at 0x002B)
+pub fn decode_baud_rate(value: [u8; 2]) -> Result<u32> {
+    let divider = u16::from_le_bytes(value);
+    BAUD_RATES
+        .iter()
+        .find(|(_, known)| *known == divider)
+        .map(|(rate, _)| *rate)
+        .ok_or_else(|| Error::InvalidData(format!("unknown baud rate divider {:04X}", divider)))
+}
+
+/// function type of a command reading registers
+pub const FUNCTION_READ: u8 = 0x07;
+
+/// function type of a command writing registers
+pub const FUNCTION_WRITE: u8 = 0x08;
+
+/// function type of the command saving the registers to flash
+pub const FUNCTION_SAVE: u8 = 0x09;
+
+/// build a command packet for the barcode scanner
+///
+/// # Arguments
+///
+/// * `function_type` the command to execute (0x07 read, 0x08 write, 0x09 save)
+/// * `length` the number of data bytes in the packet
+/// * `address` the register address the command applies to
+/// * `return_data_length` for read commands the number of bytes to read (256 at most)
+/// * `write_data` for write commands the data to write
+///
+/// # Returns
+///
+/// * the complete packet including header and checksum
+///
+pub fn encode_command(
+    function_type: u8,
+    length: u8,
+    address: u16,
+    return_data_length: Option<usize>,
+    write_data: Option<&[u8]>,
+) -> Result<Vec<u8>> {
+    let mut buffer: Vec<u8> = vec![0x7e, 0x00, function_type, length];
+    buffer.append(&mut address.to_be_bytes().to_vec());
+    if let Some(return_data_length) = return_data_length {
+        // special case, the barcode scanner expects a length of 0 when 256 bytes should be returned
+        if return_data_length == 256 {
+            buffer.push(0x00);
+        } else {
+            buffer.push(return_data_length.try_into().map_err(|_| {
+                Error::InvalidArgument(format!(
+                    "at most 256 bytes can be read at once, requested {}",
+                    return_data_length
+                ))
+            })?);
+        }
+    }
+    if let Some(write_data) = write_data {
+        buffer.append(&mut write_data.to_vec());
+    }
+    buffer.append(&mut calculate_crc(&buffer[2..])?.to_be_bytes().to_vec());
+    Ok(buffer)
+}
+
+/// build a write command packet for the barcode scanner
+pub fn encode_write_command(address: u16, data: &[u8]) -> Result<Vec<u8>> {
+    let length: u8 = data.len().try_into().map_err(|_| {
+        Error::InvalidArgument(format!(
+            "at most 255 bytes can be written at once, got {}",
+            data.len()
+        ))
+    })?;
+    encode_command(FUNCTION_WRITE, length, address, None, Some(data))
+}
+
+/// build a read command packet for the barcode scanner
+pub fn encode_read_command(address: u16, return_data_length: usize) -> Result<Vec<u8>> {
+    encode_command(FUNCTION_READ, 0x01, address, Some(return_data_length), None)
+}
+
+/// build a command packet with any function type for the barcode scanner
+///
+/// # Arguments
+///
+/// * `function_type` [`FUNCTION_READ`], [`FUNCTION_WRITE`] or [`FUNCTION_SAVE`]
+/// * `address` the register address the command applies to
+/// * `data` the data of the command (1 to 255 bytes), for reads the number of bytes to read
+pub fn encode_raw_command(function_type: u8, address: u16, data: &[u8]) -> Result<Vec<u8>> {
+    if ![FUNCTION_READ, FUNCTION_WRITE, FUNCTION_SAVE].contains(&function_type) {
+        return Err(Error::InvalidArgument(format!(
+            "unsupported function type {:02X}",
+            function_type
+        )));
+    }
+    let length: u8 = match data.len() {
+        1..=255 => data.len() as u8,
+        length => {
+            return Err(Error::InvalidArgument(format!(
+                "a command carries 1 to 255 bytes of data, got {}",
+                length
+            )));
+        }
+    };
+    encode_command(function_type, length, address, None, Some(data))
+}
+
+/// verify a command reply packet and extract its payload
+///
+/// # Arguments
+///
+/// * `buffer` the complete reply packet as read from the barcode scanner
+/// * `read_data` receives the payload, needs to be allocated to the size of payload data expected
+///
+/// # Returns
+///
+/// * the number of payload bytes
+///
+pub fn decode_reply(buffer: &[u8], read_data: &mut [u8]) -> Result<usize> {
+    if buffer.len() < REPLY_OVERHEAD {
+        // not enough data read
+        return Err(Error::UnexpectedLength {
+            expected: read_data.len() + REPLY_OVERHEAD,
+            received: buffer.len(),
+        });
+    }
+    if buffer[..2] != REPLY_HEADER {
+        // invalid header
+        return Err(Error::BadHeader);
+    }
+    if buffer[2] != 0x00 {
+        // operation was not successful
+        return Err(Error::DeviceRejected(buffer[2]));
+    }
+    // verify the checksum
+    let received_checksum =
+        ((buffer[buffer.len() - 2] as u16) << 8) | buffer[buffer.len() - 1] as u16;
+    verify_crc(&buffer[2..buffer.len() - 2], received_checksum)?;
+    let mut data_length: usize = buffer[3] as usize;
+    // special case, the barcode scanner returns a length of 0 when 256 bytes have been returned
+    if data_length == 0 {
+        data_length = 256;
+    }
+    if data_length > read_data.len() || data_length + REPLY_OVERHEAD > buffer.len() {
+        return Err(Error::UnexpectedLength {
+            expected: read_data.len(),
+            received: data_length,
+        });
+    }
+    read_data[..data_length].copy_from_slice(&buffer[4..data_length + 4]);
+    Ok(data_length)
+}
+
+/// first bytes of a command packet
+pub const COMMAND_HEADER: [u8; 2] = [0x7E, 0x00];
+
+/// add the checksum to a command packet if it is missing
+///
+/// A packet consisting of header, function type, length, address and as many data bytes as
+/// given by the length field gets the checksum appended, any other packet is returned as it is.
+///
+/// # Examples
+///
+/// ```
+/// use waveshare_barcodescanner::protocol::complete_command;
+///
+/// let command = complete_command(&[0x7E, 0x00, 0x07, 0x01, 0x00, 0xE1, 0x01]).unwrap();
+/// assert_eq!(command, [0x7E, 0x00, 0x07, 0x01, 0x00, 0xE1, 0x01, 0x22, 0xC2]);
+/// ```
+pub fn complete_command(packet: &[u8]) -> Result<Vec<u8>> {
+    if packet.len() < 4 || packet[..2] != COMMAND_HEADER {
+        return Err(Error::InvalidArgument(format!(
+            "a command starts with {:02X?}, function type and length",
+            COMMAND_HEADER
+        )));
+    }
+    let mut command = packet.to_vec();
+    if packet.len() == packet[3] as usize + 6 {
+        command.extend(calculate_crc(&packet[2..])?.to_be_bytes());
+    }
+    Ok(command)
+}
+
+/// fields of a command reply packet, without interpreting the status
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplyFrame {
+    /// status of the command, 0x00 on success
+    pub status: u8,
+    /// number of data bytes, a length field of 0 is returned as 256
+    pub length: usize,
+    /// data of the reply
+    pub data: Vec<u8>,
+    /// checksum received with the reply
+    pub checksum: u16,
+    /// checksum calculated over status, length and data
+    pub calculated_checksum: u16,
+}
+
+impl ReplyFrame {
+    /// split a complete reply packet into its fields
+    ///
+    /// the header and the length are verified, the status and the checksum are not
+    pub fn decode(packet: &[u8]) -> Result<Self> {
+        if packet.len() < REPLY_OVERHEAD {
+            return Err(Error::UnexpectedLength {
+                expected: REPLY_OVERHEAD,
+                received: packet.len(),
+            });
+        }
+        if packet[..2] != REPLY_HEADER {
+            return Err(Error::BadHeader);
+        }
+        // special case, the barcode scanner returns a length of 0 when 256 bytes have been returned
+        let length = match packet[3] {
+            0 => 256,
+            length => length as usize,
+        };
+        if packet.len() != length + REPLY_OVERHEAD {
+            return Err(Error::UnexpectedLength {
+                expected: length + REPLY_OVERHEAD,
+                received: packet.len(),
+            });
+        }
+        Ok(ReplyFrame {
+            status: packet[2],
+            length,
+            data: packet[4..packet.len() - 2].to_vec(),
+            checksum: u16::from_be_bytes([packet[packet.len() - 2], packet[packet.len() - 1]]),
+            calculated_checksum: calculate_crc(&packet[2..packet.len() - 2])?,
+        })
+    }
+
+    /// return true if the command was executed successfully
+    pub fn is_success(&self) -> bool {
+        self.status == 0x00
+    }
+
+    /// return true if the received checksum matches the content
+    pub fn checksum_ok(&self) -> bool {
+        self.checksum == self.calculated_checksum
+    }
+}
+
+impl std::fmt::Display for ReplyFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "status {:02X} ({}), length {}, data {:02X?}, CRC {:04X} ",
+            self.status,
+            if self.is_success() { "ok" } else { "rejected" },
+            self.length,
+            self.data,
+            self.checksum
+        )?;
+        if self.checksum_ok() {
+            write!(f, "(ok)")
+        } else {
+            write!(f, "(mismatch, calculated {:04X})", self.calculated_checksum)
+        }
+    }
+}
+
+/// first bytes of a command reply packet
+pub const REPLY_HEADER: [u8; 2] = [0x02, 0x00];
+
+/// output of the [`ReplyDecoder`] for the bytes fed into it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecoderEvent {
+    /// bytes which are not part of a reply packet, in the order they were received
+    Garbage(Vec<u8>),
+    /// a complete reply packet with a valid checksum, including header and checksum
+    Reply(Vec<u8>),
+}
+
+/// incremental decoder for command reply packets
+///
+/// Bytes are fed in one at a time, the decoder searches for the reply header, reads the length
+/// field and collects the rest of the packet. Bytes in front of a header are returned as
+/// [`DecoderEvent::Garbage`], so stray data on the line does not misalign the next reply.
+/// After a complete packet (valid or not) the decoder is ready for the next one.
+///
+/// # Examples
+///
+/// ```
+/// use waveshare_barcodescanner::protocol::{DecoderEvent, ReplyDecoder};
+///
+/// let mut decoder = ReplyDecoder::new();
+/// let mut events = Vec::new();
+/// for byte in [0x41, 0x02, 0x00, 0x00, 0x01, 0x00, 0x33, 0x31] {
+///     if let Some(event) = decoder.push(byte) {
+///         events.push(event.unwrap());
+///     }
+/// }
+/// assert_eq!(
+///     events,
+///     [
+///         DecoderEvent::Garbage(vec![0x41]),
+///         DecoderEvent::Reply(vec![0x02, 0x00, 0x00, 0x01, 0x00, 0x33, 0x31]),
+///     ]
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ReplyDecoder {
+    /// bytes of the reply packet received so far, starting with the header
+    buffer: Vec<u8>,
+    /// complete packets are returned without verifying their checksum
+    skip_checksum: bool,
+}
+
+impl ReplyDecoder {
+    /// create a decoder waiting for the start of a reply packet
+    pub fn new() -> Self {
+        ReplyDecoder::default()
+    }
+
+    /// create a decoder which returns complete reply packets even if their checksum is wrong
+    ///
+    /// used to inspect corrupted replies, see [`ReplyFrame::checksum_ok`]
+    pub fn without_checksum() -> Self {
+        ReplyDecoder {
+            skip_checksum: true,
+            ..ReplyDecoder::default()
+        }
+    }
+
+    /// return true if no partial reply packet has been received
+    pub fn is_idle(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// drop a partial reply packet and return its bytes
+    pub fn reset(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buffer)
+    }
+
+    /// feed the next byte received from the barcode scanner into the decoder
+    ///
+    /// # Returns
+    ///
+    /// * `None` if more data is needed
+    /// * the bytes dropped while searching for the reply header
+    /// * a complete reply packet
+    /// * [`Error::ChecksumMismatch`] if a complete reply packet is corrupted, the packet is dropped
+    ///   (unless created with [`ReplyDecoder::without_checksum`])
+    pub fn push(&mut self, byte: u8) -> Option<Result<DecoderEvent>> {
+        match self.buffer.len() {
+            0 if byte == REPLY_HEADER[0] => {
+                self.buffer.push(byte);
+                None
+            }
+            0 => Some(Ok(DecoderEvent::Garbage(vec![byte]))),
+            1 if byte == REPLY_HEADER[1] => {
+                self.buffer.push(byte);
+                None
+            }
+            1 => {
+                // not a header, the byte itself might start the next one
+                let mut garbage = self.reset();
+                if byte == REPLY_HEADER[0] {
+                    self.buffer.push(byte);
+                } else {
+                    garbage.push(byte);
+                }
+                Some(Ok(DecoderEvent::Garbage(garbage)))
+            }
+            _ => {
+                self.buffer.push(byte);
+                if self.buffer.len() < REPLY_OVERHEAD || self.buffer.len() < self.packet_length() {
+                    return None;
+                }
+                let packet = self.reset();
+                if self.skip_checksum {
+                    return Some(Ok(DecoderEvent::Reply(packet)));
+                }
+                let received_checksum =
+                    ((packet[packet.len() - 2] as u16) << 8) | packet[packet.len() - 1] as u16;
+                Some(
+                    verify_crc(&packet[2..packet.len() - 2], received_checksum)
+                        .map(|_| DecoderEvent::Reply(packet)),
+                )
+            }
+        }
+    }
+
+    /// total length of the reply packet based on its length field
+    fn packet_length(&self) -> usize {
+        // special case, the barcode scanner returns a length of 0 when 256 bytes have been returned
+        match self.buffer[3] {
+            0 => 256 + REPLY_OVERHEAD,
+            length => length as usize + REPLY_OVERHEAD,
+        }
+    }
+}
+
+/// number of bytes a scan frame adds to the barcode data (header, length, checksum)
+pub const SCAN_FRAME_OVERHEAD: usize = 5;
+
+/// first bytes of a scan frame sent in protocol output mode
+pub const SCAN_FRAME_HEADER: [u8; 2] = [0x03, 0x00];
+
+/// return the number of data bytes of a scan frame from its length byte
+pub fn scan_frame_data_length(length: u8) -> usize {
+    // special case, a length of 0 is used for 256 bytes of data
+    if length == 0 { 256 } else { length as usize }
+}
+
+/// build a scan frame as sent by the barcode scanner in protocol output mode
+///
+/// # Arguments
+///
+/// * `code_id` the code ID of the barcode type
+/// * `data` the barcode payload, 255 bytes at most
+///
+/// # Returns
+///
+/// * the complete frame including header and checksum
+///
+pub fn encode_scan_frame(code_id: u8, data: &[u8]) -> Result<Vec<u8>> {
+    if data.len() > 255 {
+        return Err(Error::InvalidArgument(format!(
+            "at most 255 bytes fit into a scan frame, got {}",
+            data.len()
+        )));
+    }
+    let mut buffer: Vec<u8> = SCAN_FRAME_HEADER.to_vec();
+    // the length includes the code ID, 256 bytes are encoded as 0
+    buffer.push((data.len() + 1) as u8);
+    buffer.push(code_id);
+    buffer.extend_from_slice(data);
+    buffer.append(&mut calculate_crc(&buffer[2..])?.to_be_bytes().to_vec());
+    Ok(buffer)
+}
+
+/// verify a scan frame and extract the code ID and the barcode payload
+///
+/// # Arguments
+///
+/// * `buffer` the complete scan frame as read from the barcode scanner
+///
+/// # Returns
+///
+/// * the code ID and the payload of the barcode
+///
+pub fn decode_scan_frame(buffer: &[u8]) -> Result<(u8, Vec<u8>)> {
+    if buffer.len() < SCAN_FRAME_OVERHEAD {
+        return Err(Error::UnexpectedLength {
+            expected: SCAN_FRAME_OVERHEAD,
+            received: buffer.len(),
+        });
+    }
+    if buffer[..2] != SCAN_FRAME_HEADER {
+        return Err(Error::BadHeader);
+    }
+    let data_length = scan_frame_data_length(buffer[2]);
+    if data_length + SCAN_FRAME_OVERHEAD != buffer.len() {
+        return Err(Error::UnexpectedLength {
+            expected: data_length + SCAN_FRAME_OVERHEAD,
+            received: buffer.len(),
+        });
+    }
+    let received_checksum =
+        ((buffer[buffer.len() - 2] as u16) << 8) | buffer[buffer.len() - 1] as u16;
+    verify_crc(&buffer[2..buffer.len() - 2], received_checksum)?;
+    Ok((buffer[3], buffer[4..buffer.len() - 2].to_vec()))
+}
+
+/// length of an AIM symbology identifier: `]`, the symbology character and a modifier
+pub const AIM_ID_LENGTH: usize = 3;
+
+/// split the AIM symbology identifier (e.g. `]Q1`) off the payload of a scan
+///
+/// returns None if the payload does not start with an AIM symbology identifier
+pub fn split_aim_id(data: &[u8]) -> Option<(String, &[u8])> {
+    if data.len() < AIM_ID_LENGTH
+        || data[0] != b']'
+        || !data[1..AIM_ID_LENGTH].iter().all(u8::is_ascii_alphanumeric)
+    {
+        return None;
+    }
+    let aim_id = String::from_utf8_lossy(&data[..AIM_ID_LENGTH]).into_owned();
+    Some((aim_id, &data[AIM_ID_LENGTH..]))
+}
+
+/// return the barcode type sent with `code_id`, None for unknown code IDs
+fn barcode_type(code_id: u8) -> Option<fn(Payload) -> Barcode> {
+    let barcode_type: fn(Payload) -> Barcode = match code_id {
+        0x65 => Barcode::Interleaved2of5,
+        0x66 => Barcode::Industrial2of5,
+        0x6D => Barcode::Matrix2of5,
+        0x64 => Barcode::EAN13,
+        0x44 => Barcode::EAN8,
+        0x63 => Barcode::UPCA,
+        0x45 => Barcode::UPCE,
+        0x42 => Barcode::ISBN,
+        0x6E => Barcode::ISSN,
+        0x6A => Barcode::Code128,
+        0x62 => Barcode::Code39,
+        0x69 => Barcode::Code93,
+        0x68 => Barcode::Code11,
+        0x61 => Barcode::Codabar,
+        0x67 => Barcode::MSI,
+        0x79 => Barcode::GS1Databar,
+        0x72 => Barcode::PDF417,
+        0x52 => Barcode::MicroPDF417,
+        0x51 => Barcode::QR,
+        0x71 => Barcode::MicroQR,
+        0x75 => Barcode::DotMatrix,
+        _ => return None,
+    };
+    Some(barcode_type)
+}
+
+/// return true if `code_id` identifies a barcode type known to this crate
+pub fn is_known_code_id(code_id: u8) -> bool {
+    barcode_type(code_id).is_some()
+}
+
+/// convert the payload of a scan into a barcode based on its code ID
+///
+/// barcode types not known to this crate are returned as [`Barcode::Unknown`]
+pub fn decode_barcode(code_id: u8, data: Vec<u8>) -> Result<Option<Barcode>> {
+    if data.is_empty() {
+        debug!("no barcode data was read from the device");
+        return Ok(None);
+    }
+    debug!(
+        "{} bytes of barcode data was read from the device",
+        data.len()
+    );
+    let data = Payload::new(data);
+    let barcode = match barcode_type(code_id) {
+        Some(barcode_type) => barcode_type(data),
+        None => {
+            debug!("unknown code ID {:02X}", code_id);
+            Barcode::Unknown(code_id, data)
+        }
+    };
+    debug!("{}", barcode);
+    Ok(Some(barcode))
+}
+
+/// convert a version register value into a version string
+pub fn decode_version(value: u8) -> String {
+    match value {
+        0x64 => String::from("V1.00"),
+        0x6E => String::from("V1.10"),
+        0x78 => String::from("V1.20"),
+        0x82 => String::from("V1.30"),
+        0x8C => String::from("V1.40"),
+        _ => format!("unknown {:0X}", value),
+    }
+}
+
+/// convert the software date registers (year past 2000, month, day) into a date
+pub fn decode_date(year: u8, month: u8, day: u8) -> Result<NaiveDate> {
+    // only the years past year 2000 are returned
+    let year: i32 = year as i32 + 2000;
+    let month: u32 = month as u32;
+    let day: u32 = day as u32;
+    NaiveDate::from_ymd_opt(year, month, day).ok_or_else(|| {
+        Error::InvalidData(format!(
+            "unable to construct date from year {} month {} day {}",
+            year, month, day
+        ))
+    })
+}
+
+/// encode the scan timeout in units of 100ms (register 0x0006)
+pub fn encode_scan_timeout(scan_timeout: Duration) -> Result<u8> {
+    if scan_timeout > Duration::from_millis(25500) {
+        return Err(Error::InvalidArgument(String::from(
+            "timeout is too big, maximum value is 25500 ms (25.5s)",
+        )));
+    }
+    if scan_timeout == Duration::from_millis(0) {
+        return Err(Error::InvalidArgument(String::from(
+            "this crate does not support indefinite waiting",
+        )));
+    }
+    // the range has been checked above, so the value always fits
+    Ok((scan_timeout.as_millis() / 100) as u8)
+}
+
+/// convert the value of register 0x0006 into the scan timeout
+///
+/// a value of 0 means the barcode scanner scans without a timeout, returned as a zero duration
+pub fn decode_scan_timeout(value: u8) -> Duration {
+    Duration::from_millis(value as u64 * 100)
+}
+
+/// convert the enable register of a barcode type (and its length registers) into its status
+///
+/// # Arguments
+///
+/// * `symbology` the barcode type the registers belong to
+/// * `values` the enable register, followed by the minimum and maximum length registers for
+///   barcode types with length limits
+pub fn decode_symbology_status(symbology: Symbology, values: &[u8]) -> Result<SymbologyStatus> {
+    let expected = if symbology.has_length_limits() { 3 } else { 1 };
+    if values.len() != expected {
+        return Err(Error::UnexpectedLength {
+            expected,
+            received: values.len(),
+        });
+    }
+    let enable = SymbologyEnable::decode(values[0]);
+    Ok(SymbologyStatus {
+        enabled: enable.enabled,
+        start_stop_character: symbology == Symbology::Codabar && enable.start_stop_character,
+        min_length: values.get(1).copied(),
+        max_length: values.get(2).copied(),
+    })
+}
+
+/// register writes (address, value) needed to enable/disable a barcode type
+///
+/// the optional minimum and maximum length registers directly follow the enable register,
+/// they are only written when the barcode type gets enabled
+///
+/// # Arguments
+///
+/// * `enable_address` the register holding the enable flag of the barcode type
+/// * `mode` the value of the enable register, 0 disables the barcode type
+/// * `min_length` the minimum number of characters of a valid barcode
+/// * `max_length` the maximum number of characters of a valid barcode
+pub fn barcode_type_writes(
+    enable_address: u16,
+    mode: u8,
+    min_length: Option<u8>,
+    max_length: Option<u8>,
+) -> Vec<(u16, u8)> {
+    let mut writes: Vec<(u16, u8)> = Vec::with_capacity(3);
+    if mode != 0x00 {
+        if let Some(min_length) = min_length {
+            writes.push((enable_address + 1, min_length));
+        }
+        if let Some(max_length) = max_length {
+            writes.push((enable_address + 2, max_length));
+        }
+    }
+    writes.push((enable_address, mode));
+    writes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_command() -> Result<()> {
+        assert_eq!(
+            encode_write_command(0x0060, &[0x05])?,
+            [0x7E, 0x00, 0x08, 0x01, 0x00, 0x60, 0x05, 0x2F, 0x16]
+        );
+        assert_eq!(
+            encode_read_command(0x00E1, 1)?[..7],
+            [0x7E, 0x00, 0x07, 0x01, 0x00, 0xE1, 0x01]
+        );
+        assert_eq!(encode_read_command(0x0000, 256)?[6], 0x00);
+        assert!(encode_read_command(0x0000, 257).is_err());
+        assert_eq!(
+            encode_raw_command(FUNCTION_READ, 0x00E1, &[0x01])?,
+            encode_read_command(0x00E1, 1)?
+        );
+        assert_eq!(
+            encode_raw_command(FUNCTION_WRITE, 0x0060, &[0x05])?,
+            encode_write_command(0x0060, &[0x05])?
+        );
+        assert!(encode_raw_command(0x0A, 0x0000, &[0x00]).is_err());
+        assert!(encode_raw_command(FUNCTION_SAVE, 0x0000, &[]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_baud_rate() -> Result<()> {
+        assert_eq!(encode_baud_rate(9600)?, [0x39, 0x01]);
+        assert_eq!(encode_baud_rate(115200)?, [0x1A, 0x00]);
+        assert!(encode_baud_rate(12345).is_err());
+        assert_eq!(decode_baud_rate([0xC4, 0x09])?, 1200);
+        assert!(decode_baud_rate([0x00, 0x00]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_reply() -> Result<()> {
+        let mut data = [0x00; 1];
+        assert_eq!(
+            decode_reply(&[0x02, 0x00, 0x00, 0x01, 0x00, 0x33, 0x31], &mut data)?,
+            1
+        );
+        assert!(matches!(
+            decode_reply(&[0x02, 0x00, 0x00, 0x01, 0x00, 0x33, 0x30], &mut data),
+            Err(Error::ChecksumMismatch { .. })
+        ));
+        assert!(matches!(
+            decode_reply(&[0x02, 0x01, 0x00, 0x01, 0x00, 0x33, 0x31], &mut data),
+            Err(Error::BadHeader)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_reply_frame() -> Result<()> {
+        let frame = ReplyFrame::decode(&[0x02, 0x00, 0x00, 0x01, 0x00, 0x33, 0x31])?;
+        assert!(frame.is_success() && frame.checksum_ok());
+        assert_eq!(frame.length, 1);
+        assert_eq!(frame.data, [0x00]);
+        assert_eq!(
+            frame.to_string(),
+            "status 00 (ok), length 1, data [00], CRC 3331 (ok)"
+        );
+        // neither a rejected command nor a wrong checksum is an error
+        let frame = ReplyFrame::decode(&[0x02, 0x00, 0x01, 0x01, 0x00, 0x33, 0x30])?;
+        assert!(!frame.is_success() && !frame.checksum_ok());
+        assert!(matches!(
+            ReplyFrame::decode(&[0x02, 0x00, 0x00, 0x02, 0x00, 0x33, 0x31]),
+            Err(Error::UnexpectedLength { .. })
+        ));
+        assert!(matches!(
+            ReplyFrame::decode(&[0x7E, 0x00, 0x00, 0x01, 0x00, 0x33, 0x31]),
+            Err(Error::BadHeader)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_complete_command() -> Result<()> {
+        let command = encode_write_command(0x0000, &[0x51])?;
+        assert_eq!(complete_command(&command[..command.len() - 2])?, command);
+        // packets with a checksum are sent unchanged
+        assert_eq!(complete_command(&command)?, command);
+        assert!(complete_command(&[0x02, 0x00, 0x07, 0x01]).is_err());
+        assert!(complete_command(&[0x7E, 0x00]).is_err());
+        Ok(())
+    }
+
+    /// feed all bytes into the decoder and collect its output
+    fn decode_all(decoder: &mut ReplyDecoder, data: &[u8]) -> Vec<Result<DecoderEvent>> {
+        data.iter().filter_map(|&byte| decoder.push(byte)).collect()
+    }
+
+    #[test]
+    fn test_reply_decoder() -> Result<()> {
+        const REPLY: [u8; 7] = [0x02, 0x00, 0x00, 0x01, 0x00, 0x33, 0x31];
+        let mut decoder = ReplyDecoder::new();
+        // garbage in front of the reply, including a header byte without its second byte
+        let mut data = vec![0x6A, 0x02, 0x41, 0x02, 0x02];
+        data.extend_from_slice(&REPLY[1..]);
+        let events = decode_all(&mut decoder, &data)
+            .into_iter()
+            .collect::<Result<Vec<DecoderEvent>>>()?;
+        assert_eq!(
+            events,
+            [
+                DecoderEvent::Garbage(vec![0x6A]),
+                DecoderEvent::Garbage(vec![0x02, 0x41]),
+                DecoderEvent::Garbage(vec![0x02]),
+                DecoderEvent::Reply(REPLY.to_vec()),
+            ]
+        );
+        assert!(decoder.is_idle());
+        // a corrupted reply is reported and the decoder starts over
+        let mut corrupted = REPLY;
+        corrupted[6] ^= 0x01;
+        let events = decode_all(&mut decoder, &corrupted);
+        assert!(matches!(events[..], [Err(Error::ChecksumMismatch { .. })]));
+        assert!(decoder.is_idle());
+        let events = decode_all(&mut decoder, &REPLY);
+        assert!(matches!(&events[..], [Ok(DecoderEvent::Reply(packet))] if packet[..] == REPLY));
+        // a length of 0 announces 256 bytes of data
+        let mut long_reply = vec![0x02, 0x00, 0x00, 0x00];
+        long_reply.extend_from_slice(&[0x55; 256]);
+        long_reply.extend_from_slice(&calculate_crc(&long_reply[2..])?.to_be_bytes());
+        let events = decode_all(&mut decoder, &long_reply);
+        assert!(matches!(&events[..], [Ok(DecoderEvent::Reply(packet))] if packet.len() == 262));
+        // a partial reply can be dropped
+        decode_all(&mut decoder, &REPLY[..4]);
+        assert_eq!(decoder.reset(), REPLY[..4]);
+        assert!(decoder.is_idle());
+        // a corrupted reply can be returned for inspection
+        let mut decoder = ReplyDecoder::without_checksum();
+        let events = decode_all(&mut decoder, &corrupted);
+        assert!(
+            matches!(&events[..], [Ok(DecoderEvent::Reply(packet))] if packet[..] == corrupted)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_aim_id() {
+        assert_eq!(
+            split_aim_id(b"]Q1hello"),
+            Some(("]Q1".to_string(), &b"hello"[..]))
+        );
+        assert_eq!(split_aim_id(b"]E0"), Some(("]E0".to_string(), &b""[..])));
+        assert_eq!(split_aim_id(b"hello"), None);
+        assert_eq!(split_aim_id(b"]Q"), None);
+        assert_eq!(split_aim_id(b"] 1abc"), None);
+    }
+
+    #[test]
+    fn test_decode_barcode() -> Result<()> {
+        match decode_barcode(0x44, b"96385074".to_vec())? {
+            Some(Barcode::EAN8(data)) => assert_eq!(data, "96385074"),
+            _ => panic!("expected an EAN8 barcode"),
+        }
+        match decode_barcode(0x71, b"line 1\nline 2".to_vec())? {
+            Some(Barcode::MicroQR(data)) => assert_eq!(
+                data.lines().collect::<Vec<&[u8]>>(),
+                [&b"line 1"[..], &b"line 2"[..]]
+            ),
+            _ => panic!("expected a Micro QR code"),
+        }
+        match decode_barcode(0x7A, vec![0x00, 0x0D, 0xFF])? {
+            Some(Barcode::Unknown(code_id, data)) => {
+                assert_eq!(code_id, 0x7A);
+                assert_eq!(data, &[0x00, 0x0D, 0xFF]);
+            }
+            _ => panic!("expected an unknown barcode"),
+        }
+        assert!(decode_barcode(0x51, Vec::new())?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_options_supported() {
+        assert!(output_options_supported(BARCODE_RESULT_OPTIONS));
+        assert!(output_options_supported(PROTOCOL_RESULT_OPTIONS));
+        // factory default, without code ID
+        assert!(!output_options_supported(0x01));
+        // CRLF as end character
+        assert!(!output_options_supported(0x25));
+        // with prefix
+        assert!(!output_options_supported(0x0D));
+    }
+
+    #[test]
+    fn test_scan_frame() -> Result<()> {
+        let frame = encode_scan_frame(0x6A, b"012\r345")?;
+        assert_eq!(frame[..4], [0x03, 0x00, 0x08, 0x6A]);
+        assert_eq!(frame.len(), 8 + SCAN_FRAME_OVERHEAD);
+        assert_eq!(decode_scan_frame(&frame)?, (0x6A, b"012\r345".to_vec()));
+        let mut corrupted = frame.clone();
+        corrupted[5] ^= 0x01;
+        assert!(matches!(
+            decode_scan_frame(&corrupted),
+            Err(Error::ChecksumMismatch { .. })
+        ));
+        assert!(matches!(
+            decode_scan_frame(&frame[..frame.len() - 1]),
+            Err(Error::UnexpectedLength { .. })
+        ));
+        assert!(encode_scan_frame(0x51, &[0x00; 256]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_settings() -> Result<()> {
+        assert_eq!(
+            decode_scan_timeout(encode_scan_timeout(Duration::from_millis(1500))?),
+            Duration::from_millis(1500)
+        );
+        assert_eq!(
+            decode_symbology_status(Symbology::Codabar, &[0x03, 4, 20])?,
+            SymbologyStatus {
+                enabled: true,
+                start_stop_character: true,
+                min_length: Some(4),
+                max_length: Some(20),
+            }
+        );
+        assert!(decode_symbology_status(Symbology::QR, &[0x01, 4, 20]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_barcode_type_writes() {
+        assert_eq!(
+            barcode_type_writes(0x0039, 0x01, Some(4), None),
+            [(0x003A, 4), (0x0039, 0x01)]
+        );
+        assert_eq!(
+            barcode_type_writes(0x0039, 0x00, Some(4), Some(20)),
+            [(0x0039, 0x00)]
+        );
+    }
+}
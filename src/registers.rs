@@ -0,0 +1,509 @@
+use crate::{
+    Barcodes, IlluminationMode, OperationMode, ScanArea, ScannerMode, Symbology, TargetLightMode,
+};
+
+/// mode of operation and light/buzzer parameters, see [`ScannerMode`]
+pub const MODE: u16 = 0x0000;
+
+/// scan trigger in command mode, see [`ScanTrigger`]
+pub const SCAN_TRIGGER: u16 = 0x0002;
+
+/// setting code options, see [`SettingCode`]
+pub const SETTING_CODE: u16 = 0x0003;
+
+/// scan timeout in units of 100ms, 0 for no timeout
+pub const SCAN_TIMEOUT: u16 = 0x0006;
+
+/// enable register of GS1 Databar Stacked, see [`SymbologyEnable`]
+pub const GS1_DATABAR_STACKED_ENABLE: u16 = 0x0026;
+
+/// enable register of Micro PDF417, see [`SymbologyEnable`]
+pub const MICRO_PDF417_ENABLE: u16 = 0x0029;
+
+/// baud rate divider, low byte (the high byte follows at 0x002B)
+pub const BAUD_RATE: u16 = 0x002A;
+
+/// scan area and barcodes allowed, see [`ScanAreaAndBarcodes`]
+pub const SCAN_AREA_AND_BARCODES: u16 = 0x002C;
+
+/// enable register of EAN-13, see [`SymbologyEnable`]
+pub const EAN13_ENABLE: u16 = 0x002E;
+
+/// enable register of EAN-8, see [`SymbologyEnable`]
+pub const EAN8_ENABLE: u16 = 0x002F;
+
+/// enable register of UPC-A, see [`SymbologyEnable`]
+pub const UPCA_ENABLE: u16 = 0x0030;
+
+/// enable register of UPC-E0, see [`SymbologyEnable`]
+pub const UPCE0_ENABLE: u16 = 0x0031;
+
+/// enable register of UPC-E1, see [`SymbologyEnable`]
+pub const UPCE1_ENABLE: u16 = 0x0032;
+
+/// enable register of Code 128, see [`SymbologyEnable`], minimum and maximum length follow
+pub const CODE128_ENABLE: u16 = 0x0033;
+
+/// enable register of Code 39, see [`SymbologyEnable`], minimum and maximum length follow
+pub const CODE39_ENABLE: u16 = 0x0036;
+
+/// enable register of Code 93, see [`SymbologyEnable`], minimum and maximum length follow
+pub const CODE93_ENABLE: u16 = 0x0039;
+
+/// enable register of Codabar, see [`SymbologyEnable`], minimum and maximum length follow
+pub const CODABAR_ENABLE: u16 = 0x003C;
+
+/// enable register of QR code, see [`SymbologyEnable`]
+pub const QR_ENABLE: u16 = 0x003F;
+
+/// enable register of Interleaved 2 of 5, see [`SymbologyEnable`], minimum and maximum length follow
+pub const INTERLEAVED_2OF5_ENABLE: u16 = 0x0040;
+
+/// enable register of Industrial 2 of 5, see [`SymbologyEnable`], minimum and maximum length follow
+pub const INDUSTRIAL_2OF5_ENABLE: u16 = 0x0043;
+
+/// enable register of Matrix 2 of 5, see [`SymbologyEnable`], minimum and maximum length follow
+pub const MATRIX_2OF5_ENABLE: u16 = 0x0046;
+
+/// enable register of Code 11, see [`SymbologyEnable`], minimum and maximum length follow
+pub const CODE11_ENABLE: u16 = 0x0049;
+
+/// enable register of MSI, see [`SymbologyEnable`], minimum and maximum length follow
+pub const MSI_ENABLE: u16 = 0x004C;
+
+/// enable register of GS1 Databar, see [`SymbologyEnable`]
+pub const GS1_DATABAR_ENABLE: u16 = 0x004F;
+
+/// enable register of GS1 Databar Limited, see [`SymbologyEnable`]
+pub const GS1_DATABAR_LIMITED_ENABLE: u16 = 0x0050;
+
+/// enable register of GS1 Databar Expanded, see [`SymbologyEnable`], minimum and maximum length follow
+pub const GS1_DATABAR_EXPANDED_ENABLE: u16 = 0x0051;
+
+/// enable register of DotMatrix, see [`SymbologyEnable`]
+pub const DOT_MATRIX_ENABLE: u16 = 0x0054;
+
+/// enable register of PDF417, see [`SymbologyEnable`]
+pub const PDF417_ENABLE: u16 = 0x0055;
+
+/// enable register of ISSN, see [`SymbologyEnable`]
+pub const ISSN_ENABLE: u16 = 0x0056;
+
+/// enable register of ISBN, see [`SymbologyEnable`]
+pub const ISBN_ENABLE: u16 = 0x0057;
+
+/// enable register of Micro QR, see [`SymbologyEnable`]
+pub const MICRO_QR_ENABLE: u16 = 0x005F;
+
+/// barcode result options, see [`ResultOptions`]
+pub const RESULT_OPTIONS: u16 = 0x0060;
+
+/// writing [`FACTORY_RESET_VALUE`] restores the factory defaults
+pub const FACTORY_RESET: u16 = 0x00D9;
+
+/// value written to [`FACTORY_RESET`] to restore the factory defaults
+pub const FACTORY_RESET_VALUE: u8 = 0x50;
+
+/// hardware version (read only)
+pub const HW_VERSION: u16 = 0x00E1;
+
+/// software version (read only)
+pub const SW_VERSION: u16 = 0x00E2;
+
+/// software date: year past 2000 (read only), month and day follow at 0x00E4 and 0x00E5
+pub const SW_DATE: u16 = 0x00E3;
+
+/// typed content of a single register
+pub trait Register: Sized {
+    /// address of the register
+    const ADDRESS: u16;
+
+    /// build the register content
+    fn encode(&self) -> u8;
+
+    /// interpret the register content
+    fn decode(value: u8) -> Self;
+}
+
+impl Register for ScannerMode {
+    const ADDRESS: u16 = MODE;
+
+    /// * bit 7 LED indication after a successful scan
+    /// * bit 6 buzzer after a successful scan
+    /// * bits 5-4 target light (00 off, 01 standard, 1x always on)
+    /// * bits 3-2 illumination (00 off, 01 standard, 1x always on)
+    /// * bits 1-0 operation mode (00 manual, 01 command, 10 continuous, 11 sensing)
+    fn encode(&self) -> u8 {
+        let mut value: u8 = 0x00;
+        if self.led_indication {
+            value |= 0x80;
+        }
+        if self.buzzer {
+            value |= 0x40;
+        }
+        value |= match self.target_light_mode {
+            TargetLightMode::AlwaysOff => 0x00,
+            TargetLightMode::AlwaysOn => 0x20,
+            TargetLightMode::Standard => 0x10,
+        };
+        value |= match self.illumination_mode {
+            IlluminationMode::AlwaysOff => 0x00,
+            IlluminationMode::AlwaysOn => 0x08,
+            IlluminationMode::Standard => 0x04,
+        };
+        value |= match self.operation_mode {
+            OperationMode::Manual => 0x00,
+            OperationMode::Command => 0x01,
+            OperationMode::Continuous => 0x02,
+            OperationMode::Sensing => 0x03,
+        };
+        value
+    }
+
+    fn decode(value: u8) -> Self {
+        let target_light_mode = match (value >> 4) & 0x03 {
+            0x00 => TargetLightMode::AlwaysOff,
+            0x01 => TargetLightMode::Standard,
+            _ => TargetLightMode::AlwaysOn,
+        };
+        let illumination_mode = match (value >> 2) & 0x03 {
+            0x00 => IlluminationMode::AlwaysOff,
+            0x01 => IlluminationMode::Standard,
+            _ => IlluminationMode::AlwaysOn,
+        };
+        let operation_mode = match value & 0x03 {
+            0x00 => OperationMode::Manual,
+            0x01 => OperationMode::Command,
+            0x02 => OperationMode::Continuous,
+            _ => OperationMode::Sensing,
+        };
+        ScannerMode {
+            led_indication: value & 0x80 != 0,
+            buzzer: value & 0x40 != 0,
+            target_light_mode,
+            illumination_mode,
+            operation_mode,
+        }
+    }
+}
+
+/// scan trigger in command mode (register 0x0002)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanTrigger {
+    /// bit 0, scanning is active, cleared by the device after a successful scan
+    pub scanning: bool,
+}
+
+impl Register for ScanTrigger {
+    const ADDRESS: u16 = SCAN_TRIGGER;
+
+    fn encode(&self) -> u8 {
+        self.scanning.into()
+    }
+
+    fn decode(value: u8) -> Self {
+        ScanTrigger {
+            scanning: value & 0x01 != 0,
+        }
+    }
+}
+
+/// setting code options (register 0x0003)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SettingCode {
+    /// bit 1 (inverted), settings can be changed by scanning setting codes
+    pub enabled: bool,
+    /// bit 0, the content of scanned setting codes is sent out
+    pub output_content: bool,
+    /// bits 7-2, not documented, kept as they are
+    pub reserved: u8,
+}
+
+impl Register for SettingCode {
+    const ADDRESS: u16 = SETTING_CODE;
+
+    fn encode(&self) -> u8 {
+        let mut value: u8 = self.reserved & 0xFC;
+        if !self.enabled {
+            value |= 0x02;
+        }
+        if self.output_content {
+            value |= 0x01;
+        }
+        value
+    }
+
+    fn decode(value: u8) -> Self {
+        SettingCode {
+            enabled: value & 0x02 == 0,
+            output_content: value & 0x01 != 0,
+            reserved: value & 0xFC,
+        }
+    }
+}
+
+/// scan area and barcodes allowed (register 0x002C)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanAreaAndBarcodes {
+    /// bit 3, only the center of the camera view is used
+    pub scan_area: ScanArea,
+    /// bits 2-1 (01 all, 10 default, 00 none)
+    pub barcodes: Barcodes,
+}
+
+impl Register for ScanAreaAndBarcodes {
+    const ADDRESS: u16 = SCAN_AREA_AND_BARCODES;
+
+    fn encode(&self) -> u8 {
+        let mut value: u8 = 0x00;
+        if self.scan_area == ScanArea::Center {
+            value |= 0x08;
+        }
+        value |= match self.barcodes {
+            Barcodes::EnableAll => 0x02,
+            Barcodes::DisableAll => 0x00,
+            Barcodes::Default => 0x04,
+        };
+        value
+    }
+
+    fn decode(value: u8) -> Self {
+        let scan_area = if value & 0x08 != 0 {
+            ScanArea::Center
+        } else {
+            ScanArea::All
+        };
+        let barcodes = if value & 0x02 != 0 {
+            Barcodes::EnableAll
+        } else if value & 0x04 != 0 {
+            Barcodes::Default
+        } else {
+            Barcodes::DisableAll
+        };
+        ScanAreaAndBarcodes {
+            scan_area,
+            barcodes,
+        }
+    }
+}
+
+/// enable register of a barcode type (see [`Symbology::enable_address`])
+///
+/// barcode types with length limits have the minimum and maximum length registers directly
+/// following the enable register
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SymbologyEnable {
+    /// bit 0, the barcode type is recognized
+    pub enabled: bool,
+    /// bit 1, start and stop characters are sent (Codabar only)
+    pub start_stop_character: bool,
+}
+
+impl SymbologyEnable {
+    /// build the enable register content for a barcode type
+    ///
+    /// the start/stop character flag is only kept for Codabar
+    pub fn for_symbology(symbology: Symbology, enabled: bool, start_stop_character: bool) -> Self {
+        SymbologyEnable {
+            enabled,
+            start_stop_character: enabled
+                && start_stop_character
+                && symbology == Symbology::Codabar,
+        }
+    }
+
+    /// build the register content
+    pub fn encode(&self) -> u8 {
+        let mut value: u8 = self.enabled.into();
+        if self.enabled && self.start_stop_character {
+            value |= 0x02;
+        }
+        value
+    }
+
+    /// interpret the register content
+    pub fn decode(value: u8) -> Self {
+        SymbologyEnable {
+            enabled: value & 0x01 != 0,
+            start_stop_character: value & 0x02 != 0,
+        }
+    }
+}
+
+/// end character appended to plain text barcode data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndCharacter {
+    /// carriage return (0x0D)
+    CR,
+    /// carriage return and line feed (0x0D 0x0A)
+    CRLF,
+    /// tabulator (0x09)
+    Tab,
+    /// no end character
+    None,
+}
+
+/// barcode result options (register 0x0060)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResultOptions {
+    /// bit 7, barcodes are sent in scan frames with a checksum
+    pub protocol: bool,
+    /// bits 6-5 (00 CR, 01 CRLF, 10 TAB, 11 none)
+    pub end_character: EndCharacter,
+    /// bit 4, RF information is added
+    pub rf: bool,
+    /// bit 3, the configured prefix is sent in front of the data
+    pub prefix: bool,
+    /// bit 2, the code ID is sent in front of the data
+    pub code_id: bool,
+    /// bit 1, the configured suffix is sent after the data
+    pub suffix: bool,
+    /// bit 0, the end character is sent after the data
+    pub end_character_enabled: bool,
+}
+
+impl Register for ResultOptions {
+    const ADDRESS: u16 = RESULT_OPTIONS;
+
+    fn encode(&self) -> u8 {
+        let mut value: u8 = match self.end_character {
+            EndCharacter::CR => 0x00,
+            EndCharacter::CRLF => 0x20,
+            EndCharacter::Tab => 0x40,
+            EndCharacter::None => 0x60,
+        };
+        for (flag, bit) in [
+            (self.protocol, 0x80),
+            (self.rf, 0x10),
+            (self.prefix, 0x08),
+            (self.code_id, 0x04),
+            (self.suffix, 0x02),
+            (self.end_character_enabled, 0x01),
+        ] {
+            if flag {
+                value |= bit;
+            }
+        }
+        value
+    }
+
+    fn decode(value: u8) -> Self {
+        let end_character = match (value >> 5) & 0x03 {
+            0x00 => EndCharacter::CR,
+            0x01 => EndCharacter::CRLF,
+            0x02 => EndCharacter::Tab,
+            _ => EndCharacter::None,
+        };
+        ResultOptions {
+            protocol: value & 0x80 != 0,
+            end_character,
+            rf: value & 0x10 != 0,
+            prefix: value & 0x08 != 0,
+            code_id: value & 0x04 != 0,
+            suffix: value & 0x02 != 0,
+            end_character_enabled: value & 0x01 != 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// decode every possible register value and check that encoding gives back the same value
+    fn assert_round_trip<R: Register>(mask: u8) {
+        for value in 0..=u8::MAX {
+            let value = value & mask;
+            assert_eq!(R::decode(value).encode(), value, "value {:02X}", value);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // "always on" has two encodings, 10 is written
+        for value in 0..=u8::MAX {
+            if (value >> 4) & 0x03 == 0x03 || (value >> 2) & 0x03 == 0x03 {
+                continue;
+            }
+            assert_eq!(ScannerMode::decode(value).encode(), value);
+        }
+        assert_round_trip::<ScanTrigger>(0x01);
+        assert_round_trip::<SettingCode>(0xFF);
+        // barcodes "all" and "default" both set is read as "all"
+        assert_round_trip::<ScanAreaAndBarcodes>(0x0A);
+        assert_round_trip::<ScanAreaAndBarcodes>(0x0C);
+        assert_round_trip::<ResultOptions>(0xFF);
+        // the start/stop character flag is only kept while the barcode type is enabled
+        for value in [0x00, 0x01, 0x03] {
+            assert_eq!(SymbologyEnable::decode(value).encode(), value);
+        }
+    }
+
+    #[test]
+    fn test_decode_settings() {
+        let mode = ScannerMode {
+            led_indication: true,
+            buzzer: false,
+            target_light_mode: TargetLightMode::AlwaysOn,
+            illumination_mode: IlluminationMode::Standard,
+            operation_mode: OperationMode::Sensing,
+        };
+        assert_eq!(ScannerMode::decode(mode.encode()), mode);
+        let scan_area_and_barcodes = ScanAreaAndBarcodes {
+            scan_area: ScanArea::Center,
+            barcodes: Barcodes::Default,
+        };
+        assert_eq!(
+            ScanAreaAndBarcodes::decode(scan_area_and_barcodes.encode()),
+            scan_area_and_barcodes
+        );
+    }
+
+    #[test]
+    fn test_bit_layout() {
+        let mode = ScannerMode {
+            led_indication: false,
+            buzzer: true,
+            target_light_mode: TargetLightMode::AlwaysOn,
+            illumination_mode: IlluminationMode::Standard,
+            operation_mode: OperationMode::Command,
+        };
+        assert_eq!(mode.encode(), 0x65);
+        let setting_code = SettingCode::decode(0x81);
+        assert!(setting_code.enabled);
+        assert!(setting_code.output_content);
+        assert_eq!(
+            SettingCode {
+                enabled: false,
+                output_content: false,
+                ..setting_code
+            }
+            .encode(),
+            0x82
+        );
+        assert_eq!(
+            ScanAreaAndBarcodes {
+                scan_area: ScanArea::Center,
+                barcodes: Barcodes::EnableAll
+            }
+            .encode(),
+            0x0A
+        );
+        let options = ResultOptions::decode(0x25);
+        assert_eq!(options.end_character, EndCharacter::CRLF);
+        assert!(options.code_id && options.end_character_enabled);
+        assert!(!options.protocol && !options.prefix && !options.suffix);
+        assert_eq!(
+            SymbologyEnable::for_symbology(Symbology::Codabar, true, true).encode(),
+            0x03
+        );
+        assert_eq!(
+            SymbologyEnable::for_symbology(Symbology::QR, true, true).encode(),
+            0x01
+        );
+        assert_eq!(
+            SymbologyEnable::for_symbology(Symbology::Codabar, false, true).encode(),
+            0x00
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use std::time::Duration;
+
+use crate::Error;
+
+/// class of errors a command exchange can be retried on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RetryOn {
+    /// no (complete) reply was received in time ([`Error::Timeout`])
+    Timeout,
+    /// the reply was corrupted on the line ([`Error::ChecksumMismatch`])
+    ChecksumMismatch,
+    /// the reply was malformed ([`Error::BadHeader`] or [`Error::UnexpectedLength`])
+    BadReply,
+    /// the barcode scanner did not accept the command ([`Error::DeviceRejected`])
+    DeviceRejected,
+    /// the transport reported an error ([`Error::Io`])
+    Io,
+}
+
+impl RetryOn {
+    /// return true if `err` belongs to this class of errors
+    pub fn matches(&self, err: &Error) -> bool {
+        matches!(
+            (self, err),
+            (RetryOn::Timeout, Error::Timeout)
+                | (RetryOn::ChecksumMismatch, Error::ChecksumMismatch { .. })
+                | (RetryOn::BadReply, Error::BadHeader)
+                | (RetryOn::BadReply, Error::UnexpectedLength { .. })
+                | (RetryOn::DeviceRejected, Error::DeviceRejected(_))
+                | (RetryOn::Io, Error::Io(_))
+        )
+    }
+}
+
+/// policy for repeating failed command exchanges with the barcode scanner
+///
+/// The default policy sends each command once. Writes and reads of registers are idempotent,
+/// so repeating them after a lost or corrupted reply is safe. The buffers of the transport are
+/// flushed between attempts and the delay doubles after each attempt, up to `max_backoff`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use anyhow::Result;
+/// use waveshare_barcodescanner::{
+///     interface::BarcodeScanner,
+///     retry::{RetryOn, RetryPolicy},
+/// };
+///
+/// fn main() -> Result<()> {
+///     let mut scanner = BarcodeScanner::builder()
+///         .retry_policy(
+///             RetryPolicy::new(3)
+///                 .backoff(Duration::from_millis(100))
+///                 .retry_on(&[RetryOn::Timeout, RetryOn::ChecksumMismatch]),
+///         )
+///         .open("/dev/serial0")?;
+///     scanner.allow_qr(true)?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// number of times a command is sent at most, including the first attempt
+    max_attempts: u32,
+    /// delay before the first retry
+    backoff: Duration,
+    /// upper limit of the delay between attempts
+    max_backoff: Duration,
+    /// errors which cause a retry
+    retry_on: Vec<RetryOn>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(1),
+            retry_on: vec![
+                RetryOn::Timeout,
+                RetryOn::ChecksumMismatch,
+                RetryOn::BadReply,
+            ],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// create a policy sending each command up to `max_attempts` times
+    ///
+    /// timeouts, checksum mismatches and malformed replies are retried by default
+    pub fn new(max_attempts: u32) -> Self {
+        RetryPolicy::default().max_attempts(max_attempts)
+    }
+
+    /// create a policy sending each command only once
+    pub fn none() -> Self {
+        RetryPolicy::default()
+    }
+
+    /// set the number of times a command is sent at most, values below 1 are treated as 1
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// set the delay before the first retry, it doubles with each further retry
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// set the upper limit of the delay between attempts
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// set the errors which cause a retry
+    pub fn retry_on(mut self, retry_on: &[RetryOn]) -> Self {
+        self.retry_on = retry_on.to_vec();
+        self
+    }
+
+    /// return the number of times a command is sent at most
+    pub fn get_max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// return true if a failed `attempt` (starting at 1) should be repeated
+    pub fn should_retry(&self, attempt: u32, err: &Error) -> bool {
+        attempt < self.max_attempts && self.retry_on.iter().any(|class| class.matches(err))
+    }
+
+    /// return the delay after the failed `attempt` (starting at 1)
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.backoff
+            .checked_mul(factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_policy() {
+        let policy = RetryPolicy::new(3).retry_on(&[RetryOn::Timeout]);
+        assert!(policy.should_retry(1, &Error::Timeout));
+        assert!(policy.should_retry(2, &Error::Timeout));
+        assert!(!policy.should_retry(3, &Error::Timeout));
+        assert!(!policy.should_retry(1, &Error::BadHeader));
+        assert!(!RetryPolicy::none().should_retry(1, &Error::Timeout));
+        assert_eq!(RetryPolicy::new(0).get_max_attempts(), 1);
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::new(10)
+            .backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_millis(500));
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(200));
+        assert_eq!(policy.delay(3), Duration::from_millis(400));
+        assert_eq!(policy.delay(4), Duration::from_millis(500));
+        assert_eq!(policy.delay(40), Duration::from_millis(500));
+    }
+}
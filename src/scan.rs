@@ -0,0 +1,434 @@
+use std::{
+    fmt::Display,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use chrono::{DateTime, Local};
+use tracing::debug;
+
+use crate::{
+    Barcode, Error, OperationMode, Result,
+    interface::BarcodeScanner,
+    protocol::{decode_barcode, split_aim_id},
+    transport::Transport,
+};
+
+/// a barcode read from the barcode scanner, together with the details of the scan
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use waveshare_barcodescanner::interface::BarcodeScanner;
+///
+/// fn main() -> Result<()> {
+///     let mut scanner = BarcodeScanner::new("/dev/serial0")?;
+///     scanner.start_scan()?;
+///     if let Some(scan) = scanner.read_barcode()? {
+///         println!("code ID {:02X}, raw data {:02X?}", scan.code_id, scan.raw);
+///         println!("data scanned: {}", scan.barcode);
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScanEvent {
+    /// the decoded barcode
+    pub barcode: Barcode,
+    /// the code ID byte sent by the barcode scanner
+    pub code_id: u8,
+    /// the AIM symbology identifier (e.g. `]Q1`), if the payload is prefixed with one
+    ///
+    /// see [`BarcodeScanner::set_aim_id_prefix`]
+    pub aim_id: Option<String>,
+    /// the payload as received, without code ID and end character
+    pub raw: Vec<u8>,
+    /// time the barcode was received
+    pub received_at: SystemTime,
+    /// time between the scan trigger and the barcode, None if the scan was not triggered
+    /// by the host (e.g. in continuous mode)
+    pub latency: Option<Duration>,
+}
+
+impl ScanEvent {
+    /// decode the payload of a scan received just now
+    ///
+    /// # Arguments
+    ///
+    /// * `code_id` the code ID byte identifying the barcode type
+    /// * `raw` the payload as received, without code ID and end character
+    /// * `aim_id_prefix` true if the payload starts with an AIM symbology identifier
+    /// * `triggered_at` time the scan was triggered by the host, if it was
+    ///
+    /// # Returns
+    ///
+    /// * the scan event, None if the payload is empty
+    pub fn decode(
+        code_id: u8,
+        raw: Vec<u8>,
+        aim_id_prefix: bool,
+        triggered_at: Option<Instant>,
+    ) -> Result<Option<Self>> {
+        let (aim_id, payload) = match split_aim_id(&raw) {
+            Some((aim_id, payload)) if aim_id_prefix => (Some(aim_id), payload.to_vec()),
+            _ => (None, raw.clone()),
+        };
+        let Some(barcode) = decode_barcode(code_id, payload)? else {
+            return Ok(None);
+        };
+        Ok(Some(ScanEvent {
+            barcode,
+            code_id,
+            aim_id,
+            raw,
+            received_at: SystemTime::now(),
+            latency: triggered_at.map(|triggered_at| triggered_at.elapsed()),
+        }))
+    }
+}
+
+impl Display for ScanEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let received_at: DateTime<Local> = self.received_at.into();
+        write!(
+            f,
+            "{} code ID {:02X}",
+            received_at.format("%Y-%m-%d %H:%M:%S%.3f"),
+            self.code_id
+        )?;
+        if let Some(aim_id) = &self.aim_id {
+            write!(f, " AIM ID {}", aim_id)?;
+        }
+        if let Some(latency) = self.latency {
+            write!(f, " after {}ms", latency.as_millis())?;
+        }
+        write!(f, ": {}", self.barcode)
+    }
+}
+
+/// handle to end a sequence of scans, e.g. from another thread
+///
+/// the sequence ends with the next poll of the barcode scanner, after at most one read timeout
+#[derive(Debug, Clone, Default)]
+pub struct StopHandle {
+    /// set once the scans should end
+    stopped: Arc<AtomicBool>,
+}
+
+impl StopHandle {
+    /// create a handle which is not stopped yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// end the sequence of scans
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    /// return true if the sequence of scans has been ended
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+}
+
+/// handle to cancel [`BarcodeScanner::read_barcode`] from another thread
+///
+/// A read waiting for a barcode returns [`Error::Cancelled`] within one read timeout and scanning
+/// is stopped on the device. A cancellation issued after [`BarcodeScanner::start_scan`] but before
+/// the read began is kept until the read consumes it. It is dropped when a read returns or the
+/// next scan is started, so it never applies to a later scan.
+///
+/// The handle only applies to [`BarcodeScanner::read_barcode`], [`Scans`] are ended with their
+/// [`StopHandle`] and the background reader with [`crate::background::BackgroundScanner::stop`].
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle {
+    /// set until the cancelled read has returned
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelHandle {
+    /// create a handle without a pending cancellation
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// cancel the running read, or the read following the current scan trigger
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// return true if a cancellation is pending
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// return true if a cancellation was pending, it is consumed
+    pub(crate) fn take(&self) -> bool {
+        self.cancelled.swap(false, Ordering::Relaxed)
+    }
+
+    /// drop a cancellation which does not apply to a read anymore
+    pub(crate) fn clear(&self) {
+        self.cancelled.store(false, Ordering::Relaxed);
+    }
+}
+
+/// iterator over the barcodes read by a barcode scanner
+///
+/// In command mode a scan is triggered whenever the previous one returned a barcode or the scan
+/// timeout has passed. In all other operation modes barcodes sent by the barcode scanner are
+/// read passively. Errors are returned as items, the iterator ends after a transport error or
+/// when its [`StopHandle`] has been triggered. Dropping the iterator stops scanning on the device.
+///
+/// # Examples
+///
+/// ```no_run
+/// use anyhow::Result;
+/// use waveshare_barcodescanner::interface::BarcodeScanner;
+///
+/// fn main() -> Result<()> {
+///     let mut scanner = BarcodeScanner::new("/dev/serial0")?;
+///     let scans = scanner.scans();
+///     let stop = scans.stop_handle();
+///     for scan in scans {
+///         let scan = scan?;
+///         println!("data scanned: {}", scan.barcode);
+///         if scan.barcode.to_string().ends_with("STOP") {
+///             stop.stop();
+///         }
+///     }
+///     Ok(())
+/// }
+/// ```
+pub struct Scans<'a, T: Transport> {
+    /// the barcode scanner to read from
+    scanner: &'a mut BarcodeScanner<T>,
+    /// ends the iterator when triggered
+    stop: StopHandle,
+    /// scans have to be triggered (command mode), None until the mode has been read
+    retrigger: Option<bool>,
+    /// time of the last scan trigger, None if a new scan has to be triggered
+    triggered_at: Option<Instant>,
+    /// set after a transport error
+    finished: bool,
+}
+
+impl<'a, T: Transport> Scans<'a, T> {
+    /// create an iterator reading from `scanner`
+    pub fn new(scanner: &'a mut BarcodeScanner<T>) -> Self {
+        Scans {
+            scanner,
+            stop: StopHandle::new(),
+            retrigger: None,
+            triggered_at: None,
+            finished: false,
+        }
+    }
+
+    /// return a handle to end the iteration
+    pub fn stop_handle(&self) -> StopHandle {
+        self.stop.clone()
+    }
+
+    /// return true if scans are triggered by the iterator (command mode)
+    fn retrigger(&mut self) -> Result<bool> {
+        if let Some(retrigger) = self.retrigger {
+            return Ok(retrigger);
+        }
+        let retrigger = self.scanner.get_mode()?.operation_mode == OperationMode::Command;
+        self.retrigger = Some(retrigger);
+        Ok(retrigger)
+    }
+
+    /// trigger a new scan unless the last one is still running
+    fn trigger(&mut self) -> Result<()> {
+        let scan_timeout = self.scanner.host_scan_timeout();
+        if self
+            .triggered_at
+            .is_some_and(|triggered_at| triggered_at.elapsed() < scan_timeout)
+        {
+            return Ok(());
+        }
+        self.scanner.start_scan()?;
+        self.triggered_at = Some(Instant::now());
+        Ok(())
+    }
+
+    /// read a single barcode, waiting one read timeout at most
+    fn poll(&mut self) -> Result<Option<ScanEvent>> {
+        if self.retrigger()? {
+            self.trigger()?;
+        }
+        // wait a single read timeout only, so the stop handle is checked regularly
+        let poll_timeout = self
+            .scanner
+            .transport()
+            .read_timeout()
+            .unwrap_or(Duration::from_millis(100));
+        self.scanner.read_barcode_within(poll_timeout)
+    }
+}
+
+impl<T: Transport> Iterator for Scans<'_, T> {
+    type Item = Result<ScanEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished && !self.stop.is_stopped() {
+            match self.poll() {
+                Ok(Some(scan)) => {
+                    // the device stops scanning after each barcode in command mode
+                    self.triggered_at = None;
+                    return Some(Ok(scan));
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    // errors of the underlying transport are not recoverable
+                    self.finished = matches!(err, Error::Io(_));
+                    return Some(Err(err));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<T: Transport> Drop for Scans<'_, T> {
+    fn drop(&mut self) {
+        if self.retrigger.is_none() || self.finished {
+            return;
+        }
+        if let Err(err) = self.scanner.stop_scan() {
+            debug!("failed to stop scanning: {}", err);
+        }
+    }
+}
+
+impl<T: Transport> BarcodeScanner<T> {
+    /// return an iterator over the barcodes read by the barcode scanner
+    ///
+    /// see [`Scans`]
+    pub fn scans(&mut self) -> Scans<'_, T> {
+        Scans::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::{SimulatedScanner, test_scanner};
+
+    fn scanner(operation_mode: OperationMode) -> Result<BarcodeScanner<SimulatedScanner>> {
+        test_scanner(SimulatedScanner::new(), Some(operation_mode))
+    }
+
+    #[test]
+    fn test_scan_event() -> Result<()> {
+        let mut scanner = scanner(OperationMode::Command)?;
+        scanner.set_aim_id_prefix(true);
+        scanner.transport_mut().queue_barcode(0x51, b"]Q1hello")?;
+        scanner.start_scan()?;
+        let scan = scanner.read_barcode()?.expect("expected a barcode");
+        assert!(matches!(&scan.barcode, Barcode::QR(data) if *data == "hello"));
+        assert_eq!(scan.code_id, 0x51);
+        assert_eq!(scan.aim_id.as_deref(), Some("]Q1"));
+        assert_eq!(scan.raw, b"]Q1hello");
+        assert!(scan.latency.is_some());
+        assert!(scan.to_string().contains("code ID 51 AIM ID ]Q1 after"));
+        // without a scan trigger there is no latency, a missing AIM ID is tolerated
+        let scan = ScanEvent::decode(0x62, b"CODE39".to_vec(), true, None)?.unwrap();
+        assert_eq!(scan.aim_id, None);
+        assert_eq!(scan.latency, None);
+        assert!(ScanEvent::decode(0x62, Vec::new(), true, None)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancel_read_barcode() -> Result<()> {
+        let mut scanner = scanner(OperationMode::Command)?;
+        let cancel = scanner.cancel_handle();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            cancel.cancel();
+        });
+        scanner.start_scan()?;
+        assert!(scanner.transport().is_scanning());
+        let started = Instant::now();
+        assert!(matches!(scanner.read_barcode(), Err(Error::Cancelled)));
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(!scanner.transport().is_scanning());
+        canceller.join().unwrap();
+        // the cancellation has been consumed
+        scanner.transport_mut().queue_barcode(0x62, b"CODE39")?;
+        scanner.start_scan()?;
+        assert!(scanner.read_barcode()?.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancel_before_read() -> Result<()> {
+        let mut scanner = scanner(OperationMode::Command)?;
+        scanner.start_scan()?;
+        // e.g. the application shuts down before the read began
+        scanner.cancel_handle().cancel();
+        let started = Instant::now();
+        assert!(matches!(scanner.read_barcode(), Err(Error::Cancelled)));
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(!scanner.transport().is_scanning());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancel_without_read() -> Result<()> {
+        let mut scanner = scanner(OperationMode::Command)?;
+        // a cancellation while no read is waiting does not affect a later read
+        scanner.cancel_handle().cancel();
+        scanner.transport_mut().queue_barcode(0x62, b"CODE39")?;
+        scanner.start_scan()?;
+        assert!(scanner.read_barcode()?.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_scans_command_mode() -> Result<()> {
+        let mut scanner = scanner(OperationMode::Command)?;
+        scanner.transport_mut().queue_barcode(0x51, b"FIRST")?;
+        scanner.transport_mut().queue_barcode(0x62, b"SECOND")?;
+        let barcodes = scanner
+            .scans()
+            .take(2)
+            .map(|scan| scan.map(|scan| scan.barcode))
+            .collect::<Result<Vec<Barcode>>>()?;
+        assert!(matches!(&barcodes[0], Barcode::QR(data) if *data == "FIRST"));
+        assert!(matches!(&barcodes[1], Barcode::Code39(data) if *data == "SECOND"));
+        // scanning is stopped when the iterator is dropped
+        assert!(!scanner.transport().is_scanning());
+        assert_eq!(scanner.transport().register(0x0002), 0x00);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scans_stop_handle() -> Result<()> {
+        let mut scanner = scanner(OperationMode::Continuous)?;
+        scanner.transport_mut().queue_barcode(0x51, b"QR")?;
+        let mut scans = scanner.scans();
+        let stop = scans.stop_handle();
+        let stopper = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            stop.stop();
+        });
+        match scans.next() {
+            Some(Ok(scan)) => assert!(matches!(scan.barcode, Barcode::QR(_))),
+            _ => panic!("expected a QR code"),
+        }
+        // no more barcodes, the iterator waits until it is stopped
+        assert!(scans.next().is_none());
+        stopper.join().unwrap();
+        Ok(())
+    }
+}
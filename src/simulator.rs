@@ -0,0 +1,595 @@
+use std::{collections::VecDeque, time::Duration};
+
+use tracing::{debug, trace};
+
+use crate::{
+    Error, IGNORED_CHECKSUM, Result, crc::calculate_crc, protocol::decode_baud_rate,
+    transport::Transport,
+};
+
+/// number of registers emulated by the simulator
+pub const REGISTER_FILE_SIZE: usize = 0x0200;
+
+/// software emulation of a Waveshare barcode scanner module (UART mode)
+///
+/// The simulator implements the device side of the command protocol: it parses the `0x7E 0x00`
+/// command frames, verifies their checksum, keeps a register file and answers with `0x02 0x00`
+/// reply frames. Scripted barcodes are returned when a scan is triggered, formatted according
+/// to the output options in register 0x0060.
+///
+/// # Examples
+///
+/// ```
+/// use anyhow::Result;
+/// use waveshare_barcodescanner::{interface::BarcodeScanner, simulator::SimulatedScanner};
+///
+/// fn main() -> Result<()> {
+///     let mut simulator = SimulatedScanner::new();
+///     simulator.queue_barcode(0x51, b"hello world")?;
+///     let mut scanner = BarcodeScanner::with_transport(simulator)?;
+///     scanner.start_scan()?;
+///     println!("{}", scanner.read_barcode()?.unwrap());
+///     Ok(())
+/// }
+/// ```
+pub struct SimulatedScanner {
+    /// current (volatile) register values
+    registers: Vec<u8>,
+    /// register values persisted with the save command
+    flash: Vec<u8>,
+    /// bytes received, but not yet processed as a complete command frame
+    input: Vec<u8>,
+    /// bytes waiting to be read by the host
+    output: VecDeque<u8>,
+    /// barcodes returned on the next scan triggers (code ID, payload)
+    scripted_barcodes: VecDeque<(u8, Vec<u8>)>,
+    /// time a read waits when there is no data to return
+    read_timeout: Duration,
+    /// true if the last read timed out, the line is idle in between two transmissions
+    idle: bool,
+    /// speed the host side of the connection is set to
+    host_baud_rate: u32,
+}
+
+impl Default for SimulatedScanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimulatedScanner {
+    /// create a simulator in factory default state
+    pub fn new() -> Self {
+        let registers = Self::factory_defaults();
+        SimulatedScanner {
+            flash: registers.clone(),
+            registers,
+            input: Vec::new(),
+            output: VecDeque::new(),
+            scripted_barcodes: VecDeque::new(),
+            read_timeout: Duration::from_millis(100),
+            idle: false,
+            host_baud_rate: 9600,
+        }
+    }
+
+    /// register values after a factory reset
+    fn factory_defaults() -> Vec<u8> {
+        let mut registers = vec![0x00; REGISTER_FILE_SIZE];
+        // LED indication, buzzer, standard target and illumination light, manual mode
+        registers[0x0000] = 0xD4;
+        // scan timeout 5s
+        registers[0x0006] = 0x32;
+        // 9600 baud
+        registers[0x002A] = 0x39;
+        registers[0x002B] = 0x01;
+        // default barcodes, full scan area
+        registers[0x002C] = 0x04;
+        // default barcodes enabled
+        for address in [0x002E, 0x002F, 0x0030, 0x0033, 0x0036, 0x003F, 0x0040] {
+            registers[address] = 0x01;
+        }
+        // CR as end character
+        registers[0x0060] = 0x01;
+        // hardware and software version V1.40, software date 2024-03-15
+        registers[0x00E1] = 0x8C;
+        registers[0x00E2] = 0x8C;
+        registers[0x00E3] = 24;
+        registers[0x00E4] = 3;
+        registers[0x00E5] = 15;
+        registers
+    }
+
+    /// return the current value of a register
+    pub fn register(&self, address: u16) -> u8 {
+        self.registers[address as usize]
+    }
+
+    /// overwrite the current value of a register
+    pub fn set_register(&mut self, address: u16, value: u8) {
+        self.registers[address as usize] = value;
+    }
+
+    /// return the value of a register as persisted in flash
+    pub fn flash_register(&self, address: u16) -> u8 {
+        self.flash[address as usize]
+    }
+
+    /// return the baud rate the module is configured for
+    pub fn baud_rate(&self) -> Option<u32> {
+        decode_baud_rate([self.registers[0x002A], self.registers[0x002B]]).ok()
+    }
+
+    /// true if a scan has been triggered and no barcode was returned yet
+    pub fn is_scanning(&self) -> bool {
+        self.registers[0x0002] & 0x01 != 0
+    }
+
+    /// queue a barcode to be returned on one of the next scan triggers
+    ///
+    /// in continuous and sensing mode the barcode is sent as soon as the host reads data
+    ///
+    /// # Arguments
+    ///
+    /// * `code_id` the code ID byte identifying the barcode type
+    /// * `data` the payload of the barcode
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::InvalidArgument`] if the barcode does not fit into a scan frame with the current
+    ///   output options (protocol output mode)
+    pub fn queue_barcode(&mut self, code_id: u8, data: &[u8]) -> Result<()> {
+        self.format_barcode(code_id, data)?;
+        self.scripted_barcodes.push_back((code_id, data.to_vec()));
+        Ok(())
+    }
+
+    /// send a barcode to the host immediately, regardless of a scan trigger
+    ///
+    /// this is what the module does in continuous and sensing mode, the barcode is rejected
+    /// like in [`SimulatedScanner::queue_barcode`]
+    pub fn send_barcode(&mut self, code_id: u8, data: &[u8]) -> Result<()> {
+        let payload = self.format_barcode(code_id, data)?;
+        debug!("SIMULATOR SCAN {} bytes: {:02X?}", payload.len(), payload);
+        self.output.extend(payload);
+        Ok(())
+    }
+
+    /// send the next queued barcode, it is dropped if the output options no longer fit it
+    fn send_queued_barcode(&mut self) -> bool {
+        let Some((code_id, data)) = self.scripted_barcodes.pop_front() else {
+            return false;
+        };
+        if let Err(err) = self.send_barcode(code_id, &data) {
+            debug!("SIMULATOR dropping queued barcode: {}", err);
+        }
+        true
+    }
+
+    /// format a barcode payload according to the output options in register 0x0060
+    fn format_barcode(&self, code_id: u8, data: &[u8]) -> Result<Vec<u8>> {
+        let options = self.registers[0x0060];
+        let mut payload: Vec<u8> = Vec::with_capacity(data.len() + 3);
+        if options & 0x04 != 0 {
+            payload.push(code_id);
+        }
+        payload.extend_from_slice(data);
+        if options & 0x01 != 0 {
+            match (options >> 5) & 0x03 {
+                0x00 => payload.push(0x0D),
+                0x01 => payload.extend_from_slice(&[0x0D, 0x0A]),
+                0x02 => payload.push(0x09),
+                _ => {}
+            }
+        }
+        if options & 0x80 != 0 {
+            // protocol output, wrap the payload into a scan frame (a length of 0 means 256 bytes)
+            let length = match payload.len() {
+                256 => 0x00,
+                length => u8::try_from(length).map_err(|_| {
+                    Error::InvalidArgument(format!(
+                        "a scan frame holds 256 bytes at most, the barcode needs {}",
+                        length
+                    ))
+                })?,
+            };
+            let mut frame: Vec<u8> = vec![0x03, 0x00, length];
+            frame.append(&mut payload);
+            let checksum = calculate_crc(&frame[2..]).unwrap_or(IGNORED_CHECKSUM);
+            frame.extend_from_slice(&checksum.to_be_bytes());
+            return Ok(frame);
+        }
+        Ok(payload)
+    }
+
+    /// process all complete command frames received so far
+    fn process_input(&mut self) {
+        loop {
+            // skip everything in front of a frame header
+            match self.input.windows(2).position(|w| w == [0x7E, 0x00]) {
+                Some(0) => {}
+                Some(start) => {
+                    debug!("SIMULATOR discarding {} bytes", start);
+                    self.input.drain(..start);
+                }
+                None => {
+                    let keep = usize::from(self.input.last() == Some(&0x7E));
+                    self.input.drain(..self.input.len() - keep);
+                    return;
+                }
+            }
+            if self.input.len() < 4 {
+                return;
+            }
+            let data_length = match self.input[3] {
+                0 => 256,
+                length => length as usize,
+            };
+            let frame_length = data_length + 8;
+            if self.input.len() < frame_length {
+                return;
+            }
+            let frame: Vec<u8> = self.input.drain(..frame_length).collect();
+            trace!("SIMULATOR frame {:02X?}", frame);
+            let checksum = u16::from_be_bytes([frame[frame_length - 2], frame[frame_length - 1]]);
+            if checksum != IGNORED_CHECKSUM
+                && calculate_crc(&frame[2..frame_length - 2]).ok() != Some(checksum)
+            {
+                // the module silently drops frames with a wrong checksum
+                debug!("SIMULATOR dropping frame with invalid checksum");
+                continue;
+            }
+            let address = u16::from_be_bytes([frame[4], frame[5]]) as usize;
+            self.execute(frame[2], address, &frame[6..frame_length - 2]);
+        }
+    }
+
+    /// execute a single command and queue the reply
+    fn execute(&mut self, function_type: u8, address: usize, data: &[u8]) {
+        match function_type {
+            // read registers
+            0x07 => {
+                let length = match data[0] {
+                    0 => 256,
+                    length => length as usize,
+                };
+                if address + length > REGISTER_FILE_SIZE {
+                    self.reply(0x01, &[0x00]);
+                    return;
+                }
+                let values = self.registers[address..address + length].to_vec();
+                self.reply(0x00, &values);
+            }
+            // write registers
+            0x08 => {
+                if address + data.len() > REGISTER_FILE_SIZE {
+                    self.reply(0x01, &[0x00]);
+                    return;
+                }
+                self.registers[address..address + data.len()].copy_from_slice(data);
+                self.reply(0x00, &[0x00]);
+                self.apply_register_side_effects(address, data.len());
+            }
+            // save registers to flash
+            0x09 => {
+                self.flash = self.registers.clone();
+                self.reply(0x00, &[0x00]);
+            }
+            _ => {
+                debug!("SIMULATOR unsupported function type {:02X}", function_type);
+                self.reply(0x01, &[0x00]);
+            }
+        }
+    }
+
+    /// react on writes to command registers
+    fn apply_register_side_effects(&mut self, address: usize, length: usize) {
+        let written = address..address + length;
+        if written.contains(&0x00D9) && self.registers[0x00D9] == 0x50 {
+            debug!("SIMULATOR factory reset");
+            self.registers = Self::factory_defaults();
+            return;
+        }
+        if written.contains(&0x0002) && self.is_scanning() && self.send_queued_barcode() {
+            // the module stops scanning after a successful read
+            self.registers[0x0002] = 0x00;
+        }
+    }
+
+    /// queue a reply frame
+    fn reply(&mut self, status: u8, data: &[u8]) {
+        let mut frame: Vec<u8> = vec![0x02, 0x00, status, data.len() as u8];
+        frame.extend_from_slice(data);
+        let checksum = calculate_crc(&frame[2..]).unwrap_or(IGNORED_CHECKSUM);
+        frame.extend_from_slice(&checksum.to_be_bytes());
+        debug!("SIMULATOR REPLY {} bytes: {:02X?}", frame.len(), frame);
+        self.output.extend(frame);
+    }
+}
+
+impl Transport for SimulatedScanner {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.output.is_empty() && self.idle && self.register(0x0000) & 0x02 != 0 {
+            // continuous and sensing mode, barcodes are sent without a trigger
+            self.send_queued_barcode();
+        }
+        if self.output.is_empty() {
+            std::thread::sleep(self.read_timeout);
+            self.idle = true;
+            return Err(std::io::ErrorKind::TimedOut.into());
+        }
+        self.idle = false;
+        let count = buf.len().min(self.output.len());
+        for (target, source) in buf.iter_mut().zip(self.output.drain(..count)) {
+            *target = source;
+        }
+        Ok(count)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        if self.baud_rate() != Some(self.host_baud_rate) {
+            // the module can not make sense of data sent with a different speed
+            debug!(
+                "SIMULATOR ignoring {} bytes sent with {} baud",
+                buf.len(),
+                self.host_baud_rate
+            );
+            return Ok(());
+        }
+        self.input.extend_from_slice(buf);
+        self.process_input();
+        Ok(())
+    }
+
+    fn set_read_timeout(&mut self, timeout: Duration) -> std::io::Result<()> {
+        self.read_timeout = timeout;
+        Ok(())
+    }
+
+    fn read_timeout(&self) -> std::io::Result<Duration> {
+        Ok(self.read_timeout)
+    }
+
+    fn discard_buffers(&mut self) -> std::io::Result<()> {
+        self.input.clear();
+        self.output.clear();
+        Ok(())
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> std::io::Result<()> {
+        self.host_baud_rate = baud_rate;
+        Ok(())
+    }
+}
+
+/// barcode scanner on `transport` with a short read timeout, as used by the tests
+///
+/// If `operation_mode` is given, the mode is set with LED indication and buzzer disabled and
+/// standard target and illumination light, otherwise the factory defaults are kept.
+#[cfg(test)]
+pub(crate) fn test_scanner<T: Transport>(
+    transport: T,
+    operation_mode: Option<crate::OperationMode>,
+) -> Result<crate::interface::BarcodeScanner<T>> {
+    use crate::{IlluminationMode, TargetLightMode, interface::BarcodeScanner};
+
+    let mut scanner = BarcodeScanner::builder()
+        .read_timeout(Duration::from_millis(10))
+        .with_transport(transport)?;
+    if let Some(operation_mode) = operation_mode {
+        scanner.set_mode(
+            false,
+            false,
+            TargetLightMode::Standard,
+            IlluminationMode::Standard,
+            operation_mode,
+        )?;
+    }
+    Ok(scanner)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::{
+        Barcode, Barcodes, Error, IlluminationMode, OperationMode, Result, ScanArea, ScannerMode,
+        Symbology, TargetLightMode,
+        interface::BarcodeScanner,
+        protocol::{FUNCTION_READ, FUNCTION_SAVE, FUNCTION_WRITE, complete_command},
+    };
+
+    fn scanner() -> Result<BarcodeScanner<SimulatedScanner>> {
+        test_scanner(SimulatedScanner::new(), None)
+    }
+
+    #[test]
+    fn test_versions_and_date() -> Result<()> {
+        let mut scanner = scanner()?;
+        assert_eq!(scanner.get_hw_version()?, "V1.40");
+        assert_eq!(scanner.get_sw_version()?, "V1.40");
+        assert_eq!(
+            scanner.get_sw_date()?,
+            NaiveDate::from_ymd_opt(2024, 3, 15).unwrap()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_configuration_writes() -> Result<()> {
+        let mut scanner = scanner()?;
+        scanner.set_mode(
+            false,
+            true,
+            TargetLightMode::AlwaysOn,
+            IlluminationMode::Standard,
+            OperationMode::Command,
+        )?;
+        scanner.set_scan_area_and_barcodes(ScanArea::Center, Barcodes::EnableAll)?;
+        scanner.set_scan_timeout(Duration::from_millis(2500))?;
+        scanner.allow_code93(true, Some(4), Some(20))?;
+        scanner.allow_codebar(true, true, None, None)?;
+        scanner.disable_setting_scanning()?;
+        let simulator = scanner.transport();
+        assert_eq!(simulator.register(0x0000), 0x65);
+        assert_eq!(simulator.register(0x002C), 0x0A);
+        assert_eq!(simulator.register(0x0006), 25);
+        assert_eq!(simulator.register(0x0039), 0x01);
+        assert_eq!(simulator.register(0x003A), 4);
+        assert_eq!(simulator.register(0x003B), 20);
+        assert_eq!(simulator.register(0x003C), 0x03);
+        assert_eq!(simulator.register(0x0003) & 0x03, 0x02);
+        Ok(())
+    }
+
+    #[test]
+    fn test_configuration_reads() -> Result<()> {
+        let mut scanner = scanner()?;
+        assert_eq!(
+            scanner.get_mode()?,
+            ScannerMode {
+                led_indication: true,
+                buzzer: true,
+                target_light_mode: TargetLightMode::Standard,
+                illumination_mode: IlluminationMode::Standard,
+                operation_mode: OperationMode::Manual,
+            }
+        );
+        assert_eq!(
+            scanner.get_scan_area_and_barcodes()?,
+            (ScanArea::All, Barcodes::Default)
+        );
+        assert_eq!(scanner.get_scan_timeout()?, Duration::from_secs(5));
+        assert_eq!(scanner.get_baud_rate()?, 9600);
+        assert!(scanner.is_setting_scanning_enabled()?);
+        assert!(scanner.is_allowed(Symbology::QR)?.enabled);
+        scanner.allow_code93(true, Some(4), Some(20))?;
+        scanner.set_scan_area_and_barcodes(ScanArea::Center, Barcodes::EnableAll)?;
+        scanner.disable_setting_scanning()?;
+        assert_eq!(scanner.get_scan_area()?, ScanArea::Center);
+        assert!(!scanner.is_setting_scanning_enabled()?);
+        let status = scanner.is_allowed(Symbology::Code93)?;
+        assert!(status.enabled);
+        assert_eq!((status.min_length, status.max_length), (Some(4), Some(20)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_raw_register_access() -> Result<()> {
+        let mut scanner = scanner()?;
+        scanner.write_registers(0x0010, &[0x11, 0x22, 0x33])?;
+        assert_eq!(scanner.read_registers(0x0010, 3)?, [0x11, 0x22, 0x33]);
+        // 256 registers are requested and returned with a length of 0
+        let registers = scanner.read_registers(0x0000, 256)?;
+        assert_eq!(registers.len(), 256);
+        assert_eq!(registers[0x0060], 0x05);
+        assert!(scanner.read_registers(0x0000, 0).is_err());
+        assert!(scanner.write_registers(0x0010, &[]).is_err());
+        assert_eq!(
+            scanner.send_raw_command(FUNCTION_READ, 0x00E1, &[0x01])?,
+            [0x8C]
+        );
+        scanner.send_raw_command(FUNCTION_SAVE, 0x0000, &[0x00])?;
+        assert_eq!(scanner.transport().flash_register(0x0011), 0x22);
+        assert!(matches!(
+            scanner.send_raw_command(FUNCTION_WRITE, 0xFFFF, &[0x00]),
+            Err(Error::DeviceRejected(0x01))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_raw_frame() -> Result<()> {
+        let mut scanner = scanner()?;
+        let reply = scanner.send_raw_frame(&complete_command(&[
+            0x7E, 0x00, 0x07, 0x01, 0x00, 0xE1, 0x01,
+        ])?)?;
+        assert!(reply.is_success() && reply.checksum_ok());
+        assert_eq!(reply.data, [0x8C]);
+        // a rejected command is returned as reply
+        let reply = scanner.send_raw_frame(&complete_command(&[
+            0x7E, 0x00, 0x08, 0x01, 0xFF, 0xFF, 0x00,
+        ])?)?;
+        assert_eq!(reply.status, 0x01);
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_to_flash_and_factory_reset() -> Result<()> {
+        let mut scanner = scanner()?;
+        scanner.allow_pdf417(true)?;
+        assert_eq!(scanner.transport().flash_register(0x0055), 0x00);
+        scanner.save_to_flash()?;
+        assert_eq!(scanner.transport().flash_register(0x0055), 0x01);
+        scanner.factory_reset()?;
+        assert_eq!(scanner.transport().register(0x0055), 0x00);
+        assert_eq!(scanner.transport().register(0x0060), 0x01);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scripted_scan() -> Result<()> {
+        let mut scanner = scanner()?;
+        scanner
+            .transport_mut()
+            .queue_barcode(0x51, b"first line\nsecond line")?;
+        scanner.start_scan()?;
+        match scanner.read_barcode()?.map(|scan| scan.barcode) {
+            Some(Barcode::QR(data)) => assert_eq!(
+                data.lines().collect::<Vec<&[u8]>>(),
+                [&b"first line"[..], &b"second line"[..]]
+            ),
+            _ => panic!("expected a QR code"),
+        }
+        assert!(!scanner.transport().is_scanning());
+        Ok(())
+    }
+
+    #[test]
+    fn test_protocol_output_scan() -> Result<()> {
+        let mut scanner = scanner()?;
+        scanner.set_protocol_output(true)?;
+        assert_eq!(scanner.transport().register(0x0060), 0x84);
+        scanner.transport_mut().queue_barcode(0x62, b"CODE\r39")?;
+        scanner.start_scan()?;
+        match scanner.read_barcode()?.map(|scan| scan.barcode) {
+            Some(Barcode::Code39(data)) => assert_eq!(data, b"CODE\r39"),
+            _ => panic!("expected a Code39 barcode"),
+        }
+        // code ID and data fill a scan frame (length 0), one more byte does not fit
+        scanner.transport_mut().queue_barcode(0x62, &[b'9'; 255])?;
+        assert!(matches!(
+            scanner.transport_mut().queue_barcode(0x62, &[b'9'; 256]),
+            Err(Error::InvalidArgument(_))
+        ));
+        scanner.start_scan()?;
+        match scanner.read_barcode()?.map(|scan| scan.barcode) {
+            Some(Barcode::Code39(data)) => assert_eq!(data.len(), 255),
+            _ => panic!("expected a Code39 barcode"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_timeout() -> Result<()> {
+        let mut scanner = scanner()?;
+        scanner.set_scan_timeout(Duration::from_millis(100))?;
+        scanner.start_scan()?;
+        assert!(scanner.read_barcode()?.is_none());
+        assert!(scanner.transport().is_scanning());
+        scanner.stop_scan()?;
+        assert!(!scanner.transport().is_scanning());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_checksum_is_dropped() -> Result<()> {
+        let mut simulator = SimulatedScanner::new();
+        simulator.write_all(&[0x7E, 0x00, 0x08, 0x01, 0x00, 0x02, 0x01, 0x00, 0x00])?;
+        assert!(!simulator.is_scanning());
+        simulator.write_all(&[0x7E, 0x00, 0x08, 0x01, 0x00, 0x02, 0x01, 0xAB, 0xCD])?;
+        assert!(simulator.is_scanning());
+        Ok(())
+    }
+}
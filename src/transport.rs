@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use serial2::SerialPort;
+
+/// byte oriented channel to the barcode scanner
+///
+/// The barcode scanner only needs a small set of operations from the underlying connection,
+/// implement this trait to talk to the device over something else than a local serial port
+/// (TCP bridge, pseudo terminal, in-memory fake for tests, ...).
+///
+/// # Notes
+///
+/// Implementations are expected to behave like a serial port with a read timeout:
+/// `read` blocks for at most the configured read timeout and returns an error of kind
+/// [`std::io::ErrorKind::TimedOut`] if no data arrived in the meantime.
+pub trait Transport {
+    /// read the available bytes into `buf`, returning the number of bytes read
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize>;
+
+    /// write all bytes of `buf`
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()>;
+
+    /// set the maximum time a single `read` waits for data
+    fn set_read_timeout(&mut self, timeout: Duration) -> std::io::Result<()>;
+
+    /// return the maximum time a single `read` waits for data
+    fn read_timeout(&self) -> std::io::Result<Duration>;
+
+    /// discard all pending data in the input and output buffers
+    fn discard_buffers(&mut self) -> std::io::Result<()>;
+
+    /// change the speed of the connection
+    ///
+    /// transports without a configurable speed return an error of kind
+    /// [`std::io::ErrorKind::Unsupported`] (default)
+    fn set_baud_rate(&mut self, _baud_rate: u32) -> std::io::Result<()> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+
+    /// read the exact number of bytes required to fill `buf`
+    ///
+    /// errors of the type [`std::io::ErrorKind::Interrupted`] are ignored, all other errors
+    /// (including timeouts) are returned immediately, possibly after some data has been read
+    fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
+        let mut buf = buf;
+        while !buf.is_empty() {
+            match self.read(buf) {
+                Ok(0) => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        "failed to fill whole buffer",
+                    ));
+                }
+                Ok(n) => buf = &mut buf[n..],
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Transport for SerialPort {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        SerialPort::read(self, buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        SerialPort::write_all(self, buf)
+    }
+
+    fn set_read_timeout(&mut self, timeout: Duration) -> std::io::Result<()> {
+        SerialPort::set_read_timeout(self, timeout)
+    }
+
+    fn read_timeout(&self) -> std::io::Result<Duration> {
+        SerialPort::get_read_timeout(self)
+    }
+
+    fn discard_buffers(&mut self) -> std::io::Result<()> {
+        SerialPort::discard_buffers(self)
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> std::io::Result<()> {
+        let mut settings = self.get_configuration()?;
+        settings.set_baud_rate(baud_rate)?;
+        self.set_configuration(&settings)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
+        SerialPort::read_exact(self, buf)
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        (**self).read(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        (**self).write_all(buf)
+    }
+
+    fn set_read_timeout(&mut self, timeout: Duration) -> std::io::Result<()> {
+        (**self).set_read_timeout(timeout)
+    }
+
+    fn read_timeout(&self) -> std::io::Result<Duration> {
+        (**self).read_timeout()
+    }
+
+    fn discard_buffers(&mut self) -> std::io::Result<()> {
+        (**self).discard_buffers()
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> std::io::Result<()> {
+        (**self).set_baud_rate(baud_rate)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
+        (**self).read_exact(buf)
+    }
+}